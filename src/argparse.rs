@@ -1,6 +1,9 @@
 use clap::{Args, Parser, Subcommand};
 
-use crate::pack::pack::PackAction;
+use crate::{
+    mc_info::{Loader, MCVersion, VT},
+    pack::{instance::InstanceAction, pack::PackAction},
+};
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -13,10 +16,68 @@ pub struct Arguments {
     #[arg(short, long, value_name = "SLUG|ID", group = "mod_actions")]
     pub download: Option<String>,
 
+    /// Only download versions the project marked as featured.
+    #[arg(long, requires = "download")]
+    pub featured: bool,
+
     /// Use the staging API instead of the regular API (for development)
-    #[arg(short = 'S', long)]
+    #[arg(short = 'S', long, conflicts_with = "api_url")]
     pub staging: bool,
 
+    /// Use a different modrinth compatible API, e.g. a self-hosted mirror.
+    ///
+    /// Can also be set with the MAPITO_API_URL environment variable or `api_url` in the config.
+    #[arg(long, value_name = "URL")]
+    pub api_url: Option<String>,
+
+    /// Download files from a mirror of the modrinth CDN.
+    ///
+    /// Can also be set with the MAPITO_CDN_URL environment variable or `cdn_url` in the config.
+    #[arg(long, value_name = "URL")]
+    pub cdn_url: Option<String>,
+
+    /// Read the releases of github pack entries from a different API, e.g. a local stand-in.
+    ///
+    /// Can also be set with the MAPITO_GITHUB_API_URL environment variable or `github_api_url` in the config.
+    #[arg(long, value_name = "URL")]
+    pub github_api_url: Option<String>,
+
+    /// Look up loader versions below URL/<loader> instead of the meta APIs of the loaders.
+    ///
+    /// Can also be set with the MAPITO_LOADER_META_URL environment variable or `loader_meta_url` in the config.
+    #[arg(long, value_name = "URL")]
+    pub loader_meta_url: Option<String>,
+
+    /// Use the values of a profile of the config.
+    ///
+    /// Can also be set with the MAPITO_PROFILE environment variable.
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Use this loader instead of the one in the config.
+    ///
+    /// Can also be set with the MAPITO_LOADER environment variable.
+    #[arg(long)]
+    pub loader: Option<Loader>,
+
+    /// Use this Minecraft version instead of the one in the config.
+    ///
+    /// Can also be set with the MAPITO_MC_VERSION environment variable.
+    #[arg(long, value_name = "VERSION")]
+    pub mc_version: Option<MCVersion>,
+
+    /// Use this release type instead of the one in the config.
+    ///
+    /// Can also be set with the MAPITO_RELEASE_TYPE environment variable.
+    #[arg(long, value_name = "TYPE")]
+    pub release_type: Option<VT>,
+
+    /// Download mods to this folder instead of the one in the config.
+    ///
+    /// Can also be set with the MAPITO_DOWNLOAD_PATH environment variable.
+    #[arg(long, value_name = "PATH")]
+    pub download_path: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -25,6 +86,8 @@ pub struct Arguments {
 pub enum Commands {
     /// Perform multiple different Pack actions
     Pack(PackArgs),
+    /// Work with the instances defined in the config
+    Instance(InstanceArgs),
     /// modify config in your default editor
     ///
     /// The text editor is defined by the EDITOR environment variable on Linux systems
@@ -41,3 +104,9 @@ pub struct PackArgs {
     #[command(subcommand)]
     pub pack_action: PackAction,
 }
+
+#[derive(Args)]
+pub struct InstanceArgs {
+    #[command(subcommand)]
+    pub instance_action: InstanceAction,
+}
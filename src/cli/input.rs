@@ -3,34 +3,37 @@ use std::io;
 use crate::{
     config::Configuration,
     pack::pack::{Pack, PackAction},
+    util::error::MapitoError,
 };
 
 pub fn confirm_input() -> bool {
-    println!("proceed? [Y,n]");
-    let stdin = io::stdin();
-    let buf = &mut String::new();
-    let _ = stdin.read_line(buf);
-    let chars: Vec<char> = buf.chars().collect();
+    loop {
+        println!("proceed? [Y,n]");
+        let stdin = io::stdin();
+        let buf = &mut String::new();
+        let _ = stdin.read_line(buf);
+        let chars: Vec<char> = buf.chars().collect();
 
-    let request: char = match chars.first() {
-        Some(c) => *c,
-        None => 'y',
-    };
+        let request: char = match chars.first() {
+            Some(c) => *c,
+            None => 'y',
+        };
 
-    match request {
-        'y' | 'Y' | '\n' => true,
-        'n' | 'N' => false,
-        _ => panic!("invalid option"),
+        match request {
+            'y' | 'Y' | '\n' => return true,
+            'n' | 'N' => return false,
+            _ => println!("invalid option"),
+        }
     }
 }
 /// Reads one line from stdin and returns it as sanitized string
 pub fn read_line_to_string() -> String {
     let buf = &mut String::new();
-    io::stdin().read_line(buf).expect("read_line");
+    let _ = io::stdin().read_line(buf);
     buf.to_string().replace("\n", "").replace("\"", "")
 }
 
-pub fn query_pack(action: PackAction, config: &Configuration) -> Pack {
+pub fn query_pack(action: PackAction, config: &Configuration) -> Result<Pack, MapitoError> {
     println!("Please enter the name of the Pack you want to {}", action);
     let name = read_line_to_string();
     Pack::open(&name, config)
@@ -1,7 +1,9 @@
 use std::{fmt::Display, str::FromStr};
 
 use crate::{
-    cli::input::read_line_to_string, mc_info::MVDescriptor, mrapi::client::ApiClient,
+    cli::input::read_line_to_string,
+    mc_info::{MCVersion, MVDescriptor, ProjectType},
+    mrapi::client::ApiClient,
     util::error::ApiError,
 };
 
@@ -46,18 +48,31 @@ where
     None
 }
 
-/// Prompt the user for multiple objects of type T
-pub fn prompt_multiple<T: FromStr + Display>(prompt: &str) -> Vec<T>
-where
-    T::Err: Display,
-{
-    let mut ret: Vec<T> = Vec::new();
-    println!("Enter multiple");
+/// Prompt user for a Minecraft version until they enter one modrinth knows.
+/// "latest" and "latest-snapshot" are resolved to the version they currently stand for.
+pub fn prompt_mc_version(client: &ApiClient, prompt: &str) -> Option<MCVersion> {
     loop {
-        match prompt_for::<T>(prompt) {
-            Some(obj) => ret.push(obj),
-            None => break,
-        };
+        let entered = prompt_for::<MCVersion>(prompt)?;
+        match client.resolve_mc_version(&entered) {
+            Ok(resolved) => {
+                if resolved != entered {
+                    println!("Using Minecraft {resolved} for {entered}");
+                }
+                return Some(resolved);
+            }
+            Err(e) => println!("{e}"),
+        }
+    }
+}
+
+/// Prompt user for Minecraft versions until they quit, each is checked like in
+/// [`prompt_mc_version`].
+pub fn prompt_mc_versions(client: &ApiClient, prompt: &str) -> Vec<MCVersion> {
+    let mut ret: Vec<MCVersion> = Vec::new();
+    while let Some(mc_ver) = prompt_mc_version(client, prompt) {
+        if !ret.contains(&mc_ver) {
+            ret.push(mc_ver);
+        }
         println!(
             "Currently selected {}",
             ret.iter()
@@ -65,18 +80,22 @@ where
                 .collect::<String>()
         );
     }
-
     ret
 }
 
 /// prompt user to select one item of a list.
-pub fn list_select<T: Display + Copy>(prompt: &str, options: &[T]) -> Option<T> {
+pub fn list_select<T: Display + Clone>(prompt: &str, options: &[T]) -> Option<T> {
     println!("{prompt}:");
     for (i, t) in options.iter().enumerate() {
         println!("[{i}]: {t}");
     }
-    let j = prompt_for::<usize>("Select a Number")?;
-    Some(options[j])
+    loop {
+        let j = prompt_for::<usize>("Select a Number")?;
+        match options.get(j) {
+            Some(option) => return Some(option.clone()),
+            None => println!("{j} is not one of the options"),
+        }
+    }
 }
 /// prompt user to select multiple options of a list
 pub fn list_multi_select<T: Display + Copy>(prompt: &str, options: &[T]) -> Option<Vec<T>> {
@@ -88,7 +107,13 @@ pub fn list_multi_select<T: Display + Copy>(prompt: &str, options: &[T]) -> Opti
         println!("[ ] [{i}]: {t}");
     }
     while let Some(j) = prompt_for::<usize>("Select an Option") {
-        selected[j] = !selected[j];
+        match selected.get_mut(j) {
+            Some(selected) => *selected = !*selected,
+            None => {
+                println!("{j} is not one of the options");
+                continue;
+            }
+        }
         for (i, t) in options.iter().enumerate() {
             if selected[i] {
                 println!("[x] [{i}]: {t}");
@@ -116,11 +141,37 @@ pub fn query_reader(
 ) -> Result<String, ApiError> {
     let mut offset = 0;
     let facets = match version_desc {
-        Some(vd) => Some(vec![
-            vec![("versions".to_string(), vd.mc_ver.to_string())],
-            vec![("categories".to_string(), vd.loader.to_string())],
-        ]),
-
+        Some(vd) => {
+            let project_types = [
+                ProjectType::Mod,
+                ProjectType::Plugin,
+                ProjectType::ResourcePack,
+                ProjectType::ShaderPack,
+                ProjectType::DataPack,
+            ];
+            // every project type has its own loaders, e.g. resource packs use "minecraft"
+            let mut facets = vec![
+                project_types
+                    .iter()
+                    .map(|pt| ("project_type".to_string(), pt.to_string()))
+                    .collect(),
+                project_types
+                    .iter()
+                    .flat_map(|pt| pt.loaders(vd))
+                    .map(|loader| ("categories".to_string(), loader.to_string()))
+                    .collect(),
+            ];
+            let game_versions = client.game_version_filter(vd)?;
+            if !game_versions.is_empty() {
+                facets.push(
+                    game_versions
+                        .iter()
+                        .map(|v| ("versions".to_string(), v.to_string()))
+                        .collect(),
+                );
+            }
+            Some(facets)
+        }
         None => None,
     };
     loop {
@@ -158,7 +209,10 @@ pub fn query_reader(
                         continue;
                     }
                 };
-                return Ok(slugs[i].clone());
+                match slugs.get(i) {
+                    Some(slug) => return Ok(slug.clone()),
+                    None => println!("{i} is not one of the results"),
+                }
             }
         }
     }
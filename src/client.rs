@@ -1,36 +1,42 @@
-use core::panic;
+use std::path::Path;
 
 use reqwest::blocking::Client;
 use sha2::{Digest, Sha512};
 
-use crate::util::error::ApiError;
+use crate::util::{
+    error::{ApiError, MapitoError},
+    fs::write_atomic,
+};
 
 pub trait Downloader {
-    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), ApiError>;
+    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), MapitoError>;
 }
 impl Downloader for Client {
-    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), ApiError> {
-        let body = match self.get(url).send() {
-            Ok(resp) => resp,
-            Err(e) => {
-                panic!("Failed to send request: {e}");
-            }
+    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), MapitoError> {
+        let response = self.get(url).send()?;
+        if !response.status().is_success() {
+            return Err(ApiError::from_response(response).into());
         }
-        .bytes()
-        .unwrap();
+        write_verified(path, url, hash, &response.bytes()?)
+    }
+}
 
-        println!("Checking data integrity.");
+/// Lowercase hex sha512 of `body`, the format modrinth uses for file hashes.
+pub fn sha512_hex(body: &[u8]) -> String {
+    base16ct::lower::encode_string(&Sha512::digest(body))
+}
 
-        let dl_hash = Sha512::digest(&body);
+/// Check the sha512 of a downloaded body and write it to `path` if it matches `hash`.
+pub fn write_verified(path: &str, url: &str, hash: &str, body: &[u8]) -> Result<(), MapitoError> {
+    println!("Checking data integrity.");
 
-        let hx_hash = base16ct::lower::encode_string(&dl_hash);
-        if hx_hash == hash {
-            println!("Integrity check passed.");
-            std::fs::write(path, &body).unwrap();
-        } else {
-            println!("Integrity check failed.");
-            return Err(ApiError::not_found());
-        }
-        Ok(())
+    let hx_hash = sha512_hex(body);
+    if hx_hash == hash {
+        println!("Integrity check passed.");
+        write_atomic(Path::new(path), body)?;
+    } else {
+        println!("Integrity check failed.");
+        return Err(ApiError::integrity(url, hash, &hx_hash).into());
     }
+    Ok(())
 }
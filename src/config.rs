@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     env,
     fmt::Display,
-    fs::{create_dir_all, File},
-    io::{ErrorKind, Read, Write},
-    path::PathBuf,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 use toml::{self, Table};
@@ -14,7 +15,13 @@ use crate::{
         input::confirm_input,
         interactions::{list_select, prompt_for},
     },
-    mc_info::{Loader, MCVersion, MCVersionUtils, LOADERS, VT},
+    ghapi::constants::GITHUB_API_URL,
+    mc_info::{Loader, MCVersion, LOADERS, VT},
+    mrapi::constants::{API_URL, CDN_URL, STAGING_API_URL},
+    util::{
+        error::MapitoError,
+        fs::{write_atomic, FileLock},
+    },
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,22 +31,85 @@ pub struct Configuration {
     pub download_path: String,
     pub pack_path: String,
     pub mc_ver: MCVersion,
-    pub staging: usize,
+    pub api_url: String,
+    pub max_retries: u32,
+    pub cdn_url: Option<String>,
+    /// instance folder packs are installed to, e.g. `.minecraft`
     pub install_path: Option<String>,
+    pub github_api_url: String,
+    pub loader_meta_url: Option<String>,
+    /// named game directories packs can be installed to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instances: BTreeMap<String, Instance>,
+    /// named sets of values that replace the ones above when selected with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Config values that replace the defaults of the config while the profile is selected
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_type: Option<VT>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<Loader>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_ver: Option<MCVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_path: Option<String>,
+}
+
+/// A game directory packs can be installed to with `pack install --instance`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Instance {
+    pub dir: String,
+    /// pack installed when no pack is named
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<String>,
+    /// target Minecraft version of the pack to install
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_ver: Option<MCVersion>,
+    /// loader the instance runs, packs for other loaders are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<Loader>,
 }
 
 impl Display for Configuration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Release Type: {}\nLoader: {}\nDownload Path: {}\nPack Path: {}\nMinecraft Version: {}\nStaging: {}\nInstallation Path: {}",
+            "Release Type: {}\nLoader: {}\nDownload Path: {}\nPack Path: {}\nMinecraft Version: {}\nAPI URL: {}\nCDN URL: {}\nMax Retries: {}\nInstallation Path: {}\nGitHub API URL: {}\nLoader Meta URL: {}\nInstances: {}\nProfiles: {}",
             self.release_type,
             self.loader,
             self.download_path,
             self.pack_path,
             self.mc_ver,
-            self.staging,
-            self.install_path.clone().unwrap_or("none".to_string())
+            self.api_url,
+            self.cdn_url.as_deref().unwrap_or(CDN_URL),
+            self.max_retries,
+            self.install_path.clone().unwrap_or("none".to_string()),
+            self.github_api_url,
+            self.loader_meta_url.as_deref().unwrap_or("default"),
+            match self.instances.is_empty() {
+                true => "none".to_string(),
+                false => self
+                    .instances
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            },
+            match self.profiles.is_empty() {
+                true => "none".to_string(),
+                false => self
+                    .profiles
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            }
         )
     }
 }
@@ -62,74 +132,196 @@ impl Configuration {
                 .to_owned(),
             loader: Loader::Fabric,
             mc_ver: MCVersion::latest(),
-            staging: 0,
+            api_url: API_URL.to_string(),
+            max_retries: 3,
+            cdn_url: None,
             install_path: None,
+            github_api_url: GITHUB_API_URL.to_string(),
+            loader_meta_url: None,
+            instances: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Configuration {
+    /// Replace config values with the ones the profile `name` sets.
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), MapitoError> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            return Err(MapitoError::Config(format!(
+                "no profile named '{name}' found"
+            )));
+        };
+        if let Some(release_type) = profile.release_type {
+            self.release_type = release_type;
+        }
+        if let Some(loader) = profile.loader {
+            self.loader = loader;
+        }
+        if let Some(mc_ver) = profile.mc_ver {
+            self.mc_ver = mc_ver;
+        }
+        if let Some(download_path) = profile.download_path {
+            self.download_path = download_path;
+        }
+        if let Some(install_path) = profile.install_path {
+            self.install_path = Some(install_path);
+        }
+        Ok(())
+    }
+
+    /// Override config values with the matching `MAPITO_*` environment variables.
+    /// These only apply to the current invocation and are never written back to the config file.
+    pub fn apply_env(&mut self) -> Result<(), MapitoError> {
+        self.apply_vars(|name| env::var(name).ok())
+    }
+
+    /// Override config values with the `MAPITO_*` variables `var` returns a value for.
+    fn apply_vars(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), MapitoError> {
+        if let Some(url) = var("MAPITO_API_URL") {
+            self.api_url = url;
+        }
+        if let Some(url) = var("MAPITO_CDN_URL") {
+            self.cdn_url = Some(url);
+        }
+        if let Some(url) = var("MAPITO_GITHUB_API_URL") {
+            self.github_api_url = url;
+        }
+        if let Some(url) = var("MAPITO_LOADER_META_URL") {
+            self.loader_meta_url = Some(url);
+        }
+        if let Some(release_type) = parse_var(&var, "MAPITO_RELEASE_TYPE")? {
+            self.release_type = release_type;
+        }
+        if let Some(loader) = parse_var(&var, "MAPITO_LOADER")? {
+            self.loader = loader;
+        }
+        if let Some(mc_ver) = parse_var(&var, "MAPITO_MC_VERSION")? {
+            self.mc_ver = mc_ver;
+        }
+        if let Some(path) = var("MAPITO_DOWNLOAD_PATH") {
+            self.download_path = path;
         }
+        Ok(())
     }
 }
 
-pub fn configure() -> Result<Configuration, String> {
+/// parse the value of a variable, `None` when it is not set
+fn parse_var<T>(var: impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, MapitoError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    var(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| MapitoError::Config(format!("invalid value for {name}: {e}")))
+        })
+        .transpose()
+}
+
+pub fn configure() -> Result<Configuration, MapitoError> {
     let config_dir = config_path()?;
+    let _lock = FileLock::acquire(&config_dir)?;
 
     let mut config_fd = match File::open(config_dir.as_path()) {
         Ok(v) => v,
         Err(e) => match e.kind() {
-            ErrorKind::NotFound => create_config().expect("create_config"),
-            ek => return Err(ek.to_string()),
+            ErrorKind::NotFound => create_config()?,
+            _ => return Err(e.into()),
         },
     };
 
     let mut body = String::new();
-    config_fd.read_to_string(&mut body).expect("read_to_string");
-
-    let config: Configuration = parse_config(body)?;
+    config_fd.read_to_string(&mut body)?;
 
-    let mut config_fd = File::create(config_dir.as_path()).expect("open");
+    let config: Configuration = parse_config(&body)?;
 
-    write!(&mut config_fd, "{}", toml::to_string(&config).unwrap()).expect("write config");
+    let serialized = toml::to_string(&config)?;
+    if serialized != body {
+        write_atomic(&config_dir, serialized.as_bytes())?;
+        println!("Updated config file '{}'", config_dir.display());
+    }
 
     Ok(config)
 }
 
-fn create_config() -> Result<File, std::io::Error> {
-    let config_dir = match env::home_dir() {
-        Some(path) => path.join(".config/mapito"),
-        None => {
-            return Err(std::io::Error::last_os_error());
-        }
-    };
-    create_dir_all(config_dir.as_path())?;
-    let mut config = File::create(config_dir.join("config.toml"))?;
+fn create_config() -> Result<File, MapitoError> {
+    let config_dir = config_path()?;
     let conf_val = conf_setup();
-    write!(&mut config, "{}", toml::to_string(&conf_val).unwrap())?;
+    write_atomic(&config_dir, toml::to_string(&conf_val)?.as_bytes())?;
 
-    let config = File::open(config_path().expect("config_path").as_path())?;
-    Ok(config)
+    Ok(File::open(config_dir.as_path())?)
 }
 
-fn parse_config(body: String) -> Result<Configuration, String> {
+fn parse_config(body: &str) -> Result<Configuration, MapitoError> {
     let mut config = Configuration::new();
-    let cfg_table = match body.parse::<Table>() {
-        Ok(v) => v,
-        Err(e) => return Err(e.message().to_string()),
-    };
+    let cfg_table = body.parse::<Table>()?;
 
     for (key, value) in cfg_table {
+        let invalid =
+            |e: &dyn Display| MapitoError::Config(format!("invalid value for '{key}': {e}"));
         match key.as_str() {
-            "release_type" => config.release_type = VT::from_str(value.as_str().unwrap()).unwrap(),
-            "loader" => config.loader = Loader::from_str(value.as_str().unwrap()).unwrap(),
-            "download_path" => config.download_path = value.try_into().unwrap(),
-            "pack_path" => config.pack_path = value.try_into().unwrap(),
-            "mc_ver" => config.mc_ver = value.try_into().unwrap(),
-            "staging" => config.staging = value.try_into().unwrap(),
-            "install_path" => config.install_path = Some(value.try_into().unwrap()),
+            "release_type" => config.release_type = value.try_into().map_err(|e| invalid(&e))?,
+            "loader" => config.loader = value.try_into().map_err(|e| invalid(&e))?,
+            "download_path" => config.download_path = value.try_into().map_err(|e| invalid(&e))?,
+            "pack_path" => config.pack_path = value.try_into().map_err(|e| invalid(&e))?,
+            "mc_ver" => config.mc_ver = value.try_into().map_err(|e| invalid(&e))?,
+            "api_url" => config.api_url = value.try_into().map_err(|e| invalid(&e))?,
+            "cdn_url" => config.cdn_url = Some(value.try_into().map_err(|e| invalid(&e))?),
+            // replaced by api_url, kept so older config files keep pointing at staging
+            "staging" => {
+                if value.as_integer() == Some(1) {
+                    config.api_url = STAGING_API_URL.to_string();
+                }
+            }
+            "max_retries" => config.max_retries = value.try_into().map_err(|e| invalid(&e))?,
+            "install_path" => {
+                config.install_path = Some(migrate_install_path(
+                    "install_path",
+                    value.try_into().map_err(|e| invalid(&e))?,
+                ))
+            }
+            "github_api_url" => {
+                config.github_api_url = value.try_into().map_err(|e| invalid(&e))?
+            }
+            "loader_meta_url" => {
+                config.loader_meta_url = Some(value.try_into().map_err(|e| invalid(&e))?)
+            }
+            "instances" => config.instances = value.try_into().map_err(|e| invalid(&e))?,
+            "profiles" => config.profiles = value.try_into().map_err(|e| invalid(&e))?,
             &_ => println!("Warning: unused key '{key}' in config file."),
         }
     }
+    for (name, profile) in config.profiles.iter_mut() {
+        profile.install_path = profile
+            .install_path
+            .take()
+            .map(|path| migrate_install_path(&format!("profiles.{name}.install_path"), path));
+    }
 
     Ok(config)
 }
 
+/// `install_path` used to be the mods folder, since packs also hold resource packs, shader packs
+/// and datapacks it is the instance folder holding it.
+/// `setting` names the changed value in the printed notice.
+fn migrate_install_path(setting: &str, path: String) -> String {
+    let as_path = Path::new(&path);
+    match (as_path.file_name(), as_path.parent()) {
+        (Some(name), Some(parent)) if name == "mods" && !parent.as_os_str().is_empty() => {
+            let root = parent.to_string_lossy().to_string();
+            println!(
+                "{setting} '{path}' is a mods folder, packs now install to the instance \
+                folder holding it. Changed {setting} to '{root}'."
+            );
+            root
+        }
+        _ => path,
+    }
+}
+
 fn conf_setup() -> Configuration {
     let mut default = Configuration::new();
 
@@ -147,8 +339,12 @@ fn conf_setup() -> Configuration {
                 "Download Path",
                 "Pack Path",
                 "Minecraft Version",
-                "Staging",
+                "API URL",
+                "CDN URL",
+                "Max Retries",
                 "Install Path",
+                "GitHub API URL",
+                "Loader Meta URL",
             ],
         )
         .unwrap_or("q")
@@ -157,34 +353,157 @@ fn conf_setup() -> Configuration {
         .replace(" ", "_")
         .as_str()
         {
-            "release_type" => default.release_type = prompt_for("Enter new release_type").unwrap(),
-            "loader" => default.loader = list_select("Select new Loader", LOADERS).unwrap(),
+            "release_type" => {
+                if let Some(vt) = prompt_for("Enter new release_type") {
+                    default.release_type = vt;
+                }
+            }
+            "loader" => {
+                if let Some(loader) = list_select("Select new Loader", LOADERS) {
+                    default.loader = loader;
+                }
+            }
             "download_path" => {
-                default.download_path = prompt_for("Enter new download_path").unwrap();
+                if let Some(path) = prompt_for("Enter new download_path") {
+                    default.download_path = path;
+                }
+            }
+            "pack_path" => {
+                if let Some(path) = prompt_for("Enter new pack_path") {
+                    default.pack_path = path;
+                }
             }
-            "pack_path" => default.pack_path = prompt_for("Enter new pack_path").unwrap(),
             "minecraft_version" => {
-                default.mc_ver = prompt_for("Enter new minecraft version").unwrap()
+                if let Some(ver) = prompt_for("Enter new minecraft version") {
+                    default.mc_ver = ver;
+                }
+            }
+            "api_url" => {
+                if let Some(url) = prompt_for("Enter the url of the modrinth compatible api") {
+                    default.api_url = url;
+                }
+            }
+            "cdn_url" => {
+                default.cdn_url =
+                    prompt_for("Enter the url of the CDN mirror files are fetched from")
+            }
+            "max_retries" => {
+                if let Some(retries) = prompt_for("Enter how often failed requests are retried") {
+                    default.max_retries = retries;
+                }
             }
-            "staging" => default.staging = prompt_for(
-                "Enter if the modrinth api staging server should be used (0 or 1) (for testing)",
-            )
-            .unwrap(),
             "install_path" => {
-                default.install_path =
-                    Some(prompt_for("Enter where mapito packs should install to.").unwrap())
+                default.install_path = prompt_for(
+                    "Enter the instance folder (e.g. .minecraft) mapito packs should install to.",
+                )
+            }
+            "github_api_url" => {
+                if let Some(url) =
+                    prompt_for("Enter the url of the github api releases are read from")
+                {
+                    default.github_api_url = url;
+                }
+            }
+            "loader_meta_url" => {
+                default.loader_meta_url = prompt_for(
+                    "Enter the url of a mirror of the loader meta apis, loaders are below <url>/<loader>",
+                )
             }
             "q" => break,
-            _ => panic!("Incorrect input"), //Should not be reached
+            _ => unreachable!("list_select only returns listed options"),
         }
     }
     println!("Saving config with Values:\n{default}");
     default
 }
 
-pub fn config_path() -> Result<PathBuf, String> {
+pub fn config_path() -> Result<PathBuf, MapitoError> {
     match env::home_dir() {
         Some(path) => Ok(path.join(".config/mapito/config.toml")),
-        None => Err("Home Dir not Found".to_owned()),
+        None => Err(MapitoError::Config("Home Dir not Found".to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"
+loader = "fabric"
+mc_ver = "1.21.5"
+download_path = "/mods"
+
+[profiles.legacy]
+loader = "forge"
+mc_ver = "1.20.1"
+"#;
+
+    #[test]
+    fn profiles_replace_only_the_values_they_set() {
+        let mut config = parse_config(BODY).unwrap();
+        config.apply_profile("legacy").unwrap();
+        assert_eq!(config.loader, Loader::Forge);
+        assert_eq!(config.mc_ver, "1.20.1".parse().unwrap());
+        assert_eq!(config.download_path, "/mods");
+        assert!(config.apply_profile("missing").is_err());
+    }
+
+    #[test]
+    fn mods_folders_are_migrated_in_profiles_too() {
+        let mut config = parse_config(
+            r#"
+install_path = "/games/.minecraft/mods"
+
+[profiles.server]
+install_path = "/srv/mc/mods"
+
+[profiles.modded]
+install_path = "/games/modded"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.install_path.as_deref(), Some("/games/.minecraft"));
+        config.apply_profile("server").unwrap();
+        assert_eq!(config.install_path.as_deref(), Some("/srv/mc"));
+        config.apply_profile("modded").unwrap();
+        assert_eq!(config.install_path.as_deref(), Some("/games/modded"));
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: BTreeMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn environment_overrides_the_profile() {
+        let mut config = parse_config(BODY).unwrap();
+        config.apply_profile("legacy").unwrap();
+        config
+            .apply_vars(vars(&[
+                ("MAPITO_LOADER", "quilt"),
+                ("MAPITO_RELEASE_TYPE", "beta"),
+                ("MAPITO_DOWNLOAD_PATH", "/env-mods"),
+            ]))
+            .unwrap();
+        assert_eq!(config.loader, Loader::Quilt);
+        assert_eq!(config.release_type, VT::Beta);
+        assert_eq!(config.mc_ver, "1.20.1".parse().unwrap());
+        assert_eq!(config.download_path, "/env-mods");
+
+        let invalid = config.apply_vars(vars(&[("MAPITO_LOADER", "ornithe")]));
+        assert!(matches!(invalid, Err(MapitoError::Config(_))));
+    }
+
+    #[test]
+    fn unset_variables_change_nothing() {
+        let mut config = parse_config(BODY).unwrap();
+        config.apply_vars(vars(&[])).unwrap();
+        assert_eq!(config.loader, Loader::Fabric);
+        assert!(parse_var::<Loader>(vars(&[]), "MAPITO_LOADER")
+            .unwrap()
+            .is_none());
     }
 }
@@ -0,0 +1,100 @@
+use regex::Regex;
+use reqwest::{
+    blocking::{Client, Response},
+    header::ACCEPT,
+    Url,
+};
+
+use crate::{
+    client::sha512_hex,
+    config::Configuration,
+    mc_info::VT,
+    mrapi::client::parse_json,
+    util::error::{ApiError, MapitoError},
+};
+
+use super::{
+    constants::{PER_PAGE, RELEASES, REPOS, USER_AGENT},
+    defines::{Asset, Release},
+};
+
+/// Client for the releases of github repositories
+pub struct GithubClient {
+    client: Client,
+    api_url: String,
+}
+
+impl GithubClient {
+    pub fn new(config: &Configuration) -> Result<GithubClient, MapitoError> {
+        let api_url = match Url::parse(&config.github_api_url) {
+            Ok(_) => config.github_api_url.trim_end_matches('/').to_string(),
+            Err(e) => {
+                return Err(MapitoError::Config(format!(
+                    "invalid url '{}': {e}",
+                    config.github_api_url
+                )))
+            }
+        };
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|e| MapitoError::Github(e.into()))?;
+        Ok(GithubClient { client, api_url })
+    }
+
+    fn send(&self, url: &str) -> Result<Response, ApiError> {
+        let response = self
+            .client
+            .get(url)
+            .header(ACCEPT, "application/vnd.github+json")
+            .send()?;
+        if !response.status().is_success() {
+            return Err(ApiError::from_response(response));
+        }
+        Ok(response)
+    }
+
+    /// the releases of a repository given as `owner/name`, newest first
+    pub fn get_releases(&self, repo: &str) -> Result<Vec<Release>, MapitoError> {
+        let url = Url::parse_with_params(
+            &format!("{}{REPOS}/{repo}{RELEASES}", self.api_url),
+            &[(PER_PAGE, "100")],
+        )
+        .map_err(|e| MapitoError::Config(format!("invalid repository '{repo}': {e}")))?;
+        self.send(url.as_str())
+            .and_then(parse_json)
+            .map_err(MapitoError::Github)
+    }
+
+    /// The newest published release of `repo` with an asset whose name matches `pattern`.
+    /// Pre-releases are only considered when beta or alpha versions are accepted.
+    pub fn newest_asset(
+        &self,
+        repo: &str,
+        pattern: &Regex,
+        version_types: &[VT],
+    ) -> Result<Option<(Release, Asset)>, MapitoError> {
+        let prereleases = version_types.iter().any(|vt| vt != &VT::Release);
+        Ok(self
+            .get_releases(repo)?
+            .into_iter()
+            .filter(|release| !release.draft && (prereleases || !release.prerelease))
+            .find_map(|release| {
+                let asset = release
+                    .assets
+                    .iter()
+                    .find(|asset| pattern.is_match(&asset.name))?
+                    .clone();
+                Some((release, asset))
+            }))
+    }
+
+    /// download a release asset to compute its sha512
+    pub fn hash_asset(&self, asset: &Asset) -> Result<String, MapitoError> {
+        let body = self
+            .send(&asset.browser_download_url)
+            .and_then(|response| Ok(response.bytes()?))
+            .map_err(MapitoError::Github)?;
+        Ok(sha512_hex(&body))
+    }
+}
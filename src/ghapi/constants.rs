@@ -0,0 +1,6 @@
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+pub const REPOS: &str = "/repos";
+pub const RELEASES: &str = "/releases";
+pub const PER_PAGE: &str = "per_page";
+/// github rejects requests without a user agent
+pub const USER_AGENT: &str = concat!("mapito/", env!("CARGO_PKG_VERSION"));
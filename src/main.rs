@@ -2,12 +2,19 @@ mod argparse;
 mod cli;
 mod client;
 mod config;
+mod ghapi;
 mod mc_info;
+mod metaapi;
 mod mrapi;
 mod pack;
 mod util;
 
-use std::{env::var, os::unix::process::CommandExt, process::Command};
+use std::{
+    env::var,
+    io,
+    os::unix::process::CommandExt,
+    process::{Command, ExitCode},
+};
 
 use crate::{
     cli::interactions::{list_multi_select, list_select, query_reader},
@@ -15,30 +22,85 @@ use crate::{
     config::config_path,
     mc_info::LOADERS,
     pack::pack::list_packs,
-    util::byte_to_readable,
+    util::{byte_to_readable, error::MapitoError, fs::FileLock},
 };
 
 use argparse::Commands;
 use clap::Parser;
 use cli::{
     input::{confirm_input, query_pack, read_line_to_string},
-    interactions::{prompt_for, search_mods},
+    interactions::{prompt_for, prompt_mc_version, prompt_mc_versions, search_mods},
 };
 use config::{configure, Configuration};
-use mc_info::{Loader, MCVersion, MVDescriptor, VT};
-use mrapi::{client::ApiClient, defines::Version};
+use ghapi::client::GithubClient;
+use mc_info::{Loader, MCVersion, MCVersionRange, MVDescriptor, ProjectType, VT};
+use metaapi::client::MetaClient;
+use mrapi::{
+    client::ApiClient,
+    constants::STAGING_API_URL,
+    defines::{ApiFile, Version},
+};
 use pack::{
     create_pack,
-    pack::{Pack, PackAction},
-    update_pack,
+    export::{export_prism, export_server, zip_export},
+    instance::{install_instance, list_instances, InstanceAction},
+    launcher::install_launcher_profile,
+    pack::{ExportFormat, Pack, PackAction, PackFormat},
+    packwiz::{export_packwiz, import_packwiz},
+    pin_loader, update_pack,
 };
 
-fn main() {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            e.exit_code()
+        }
+    }
+}
+
+fn run() -> Result<(), MapitoError> {
     //variables set by arguments
-    let config = configure().expect("configure");
+    let mut config = configure()?;
     let project_slug: String = String::new();
     let parser = argparse::Arguments::parse();
-    let api_client = ApiClient::new(parser.staging);
+    if let Some(profile) = parser
+        .profile
+        .clone()
+        .or_else(|| var("MAPITO_PROFILE").ok())
+    {
+        config.apply_profile(&profile)?;
+    }
+    config.apply_env()?;
+    if parser.staging {
+        config.api_url = STAGING_API_URL.to_string();
+    }
+    if let Some(api_url) = parser.api_url {
+        config.api_url = api_url;
+    }
+    if let Some(cdn_url) = parser.cdn_url {
+        config.cdn_url = Some(cdn_url);
+    }
+    if let Some(github_api_url) = parser.github_api_url {
+        config.github_api_url = github_api_url;
+    }
+    if let Some(loader_meta_url) = parser.loader_meta_url {
+        config.loader_meta_url = Some(loader_meta_url);
+    }
+    if let Some(loader) = parser.loader {
+        config.loader = loader;
+    }
+    if let Some(mc_ver) = parser.mc_version {
+        config.mc_ver = mc_ver;
+    }
+    if let Some(release_type) = parser.release_type {
+        config.release_type = release_type;
+    }
+    if let Some(download_path) = parser.download_path {
+        config.download_path = download_path;
+    }
+    let api_client = ApiClient::new(&config)?;
 
     if let Some(search) = parser.search {
         println!(
@@ -50,48 +112,67 @@ fn main() {
             &api_client,
             Some(&MVDescriptor {
                 mc_ver: config.mc_ver,
-                version_types: vec![VT::Release, VT::Beta, VT::Alpha],
+                mc_range: None,
+                targets: Vec::new(),
+                version_types: vec![config.release_type],
                 loader: config.loader,
+                loader_version: None,
+                accepted_loaders: Vec::new(),
             }),
         ) {
             Ok(s) => s,
             Err(e) => {
                 println!("{}", e);
-                return;
+                return Ok(());
             }
         };
-        api_client.print_project_info(&slug);
-        return;
+        api_client.print_project_info(&slug)?;
+        return Ok(());
     }
 
     if let Some(dl_id) = parser.download {
         let version_desc = MVDescriptor {
             mc_ver: config.mc_ver,
+            mc_range: None,
+            targets: Vec::new(),
             version_types: vec![config.release_type],
             loader: config.loader,
+            loader_version: None,
+            accepted_loaders: Vec::new(),
         };
-        let dl_version: Version = match api_client.get_project_version(&dl_id, &version_desc) {
-            Ok(v) => v,
-            Err(e) => {
-                println!("get_project_version: {}", e);
-                return;
-            }
-        };
+        let featured = parser.featured.then_some(true);
+        let project_type =
+            ProjectType::of_project(&api_client.get_project(&dl_id)?, &version_desc.loader);
+        let dl_version: Version =
+            api_client.get_project_version(&dl_id, &version_desc, project_type, featured)?;
+
+        let dl_file = dl_version.primary_file()?.clone();
+        let dl_sha512 = dl_file.sha512()?.to_string();
 
-        let mut dependencies: Vec<Version> = Vec::new();
+        let mut dependencies: Vec<(String, ApiFile)> = Vec::new();
         for dependency in dl_version.dependencies {
-            let dep_ver =
-                match api_client.get_project_version(&dependency.project_id, &version_desc) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        println!("get_project_version: {}", e);
-                        continue;
-                    }
-                };
-            dependencies.push(dep_ver);
+            let dep_ver = match api_client.get_project_version(
+                &dependency.project_id,
+                &version_desc,
+                ProjectType::Mod,
+                None,
+            ) {
+                Ok(v) => v,
+                Err(e) => {
+                    println!("get_project_version: {}", e);
+                    continue;
+                }
+            };
+            match dep_ver
+                .primary_file()
+                .and_then(|file| file.sha512().map(|_| file))
+            {
+                Ok(file) => dependencies.push((dep_ver.name.clone(), file.clone())),
+                Err(e) => println!("Skipping dependency: {e}"),
+            }
         }
 
-        let dl_size = byte_to_readable(dl_version.files[0].size);
+        let dl_size = byte_to_readable(dl_file.size);
         println!(
             "Downloading: {}, {}\ntype: {}, downloads: {}, loader: {:?}\nsize: {}",
             dl_version.name,
@@ -104,21 +185,11 @@ fn main() {
 
         if confirm_input() {
             println!("Downloading to {}", &config.download_path);
-            let filename = dl_version.files[0].filename.as_str();
-            let path = &(config.download_path.clone() + "/" + filename);
-            api_client
-                .download_file(
-                    path,
-                    dl_version.files[0].url.as_str(),
-                    dl_version.files[0].hashes["sha512"]
-                        .to_string()
-                        .replace("\"", "")
-                        .as_str(),
-                )
-                .unwrap();
+            let path = &(config.download_path.clone() + "/" + &dl_file.filename);
+            api_client.download_file(path, &dl_file.url, &dl_sha512)?;
         } else {
             println!("Aborting");
-            return;
+            return Ok(());
         }
 
         if !dependencies.is_empty() {
@@ -126,103 +197,190 @@ fn main() {
                 "Found the following dependencies:\n {}",
                 dependencies
                     .iter()
-                    .map(|dep| dep.name.clone()
-                        + ", "
-                        + &byte_to_readable(dep.files[0].size)
-                        + "\n")
+                    .map(|(name, file)| name.clone() + ", " + &byte_to_readable(file.size) + "\n")
                     .collect::<String>()
             );
             println!("Download these too?");
             if confirm_input() {
-                for dep in dependencies {
-                    println!("Downloading {}", dep.name);
-                    let filename = dep.files[0].filename.as_str();
-                    let path = &(config.download_path.clone() + "/" + filename);
-                    api_client
-                        .download_file(
-                            path,
-                            dep.files[0].url.as_str(),
-                            dep.files[0].hashes["sha512"]
-                                .to_string()
-                                .replace("\"", "")
-                                .as_str(),
-                        )
-                        .unwrap();
+                for (name, file) in dependencies {
+                    println!("Downloading {}", name);
+                    let path = &(config.download_path.clone() + "/" + &file.filename);
+                    api_client.download_file(path, &file.url, file.sha512()?)?;
                 }
             }
         }
-        return;
+        return Ok(());
     }
 
     if !project_slug.is_empty() {
-        api_client.print_project_info(&project_slug);
-        return;
+        api_client.print_project_info(&project_slug)?;
+        return Ok(());
     }
 
     match &parser.command {
-        Some(Commands::Pack(action)) => match action.pack_action {
-            PackAction::Create => pack_creation_loop(&api_client, &config),
+        Some(Commands::Pack(action)) => match &action.pack_action {
+            PackAction::Create => pack_creation_loop(&api_client, &config)?,
             PackAction::Update => {
                 println!("Please enter the name of the Pack you want to Update");
                 let name = read_line_to_string();
-                update_pack(&api_client, name, &config).expect("update_pack");
+                update_pack(&api_client, name, &config)?;
+            }
+            PackAction::Modify => pack_modification_loop(&api_client, &config)?,
+            PackAction::Install {
+                pack,
+                instance: Some(instance),
+                mc,
+                side,
+                force,
+                ..
+            } => install_instance(
+                instance,
+                pack.as_deref(),
+                &api_client,
+                &config,
+                mc.as_ref(),
+                *side,
+                *force,
+            )?,
+            PackAction::Install {
+                pack,
+                mc,
+                force,
+                launcher_profile: true,
+                ..
+            } => {
+                let mut pack = match pack {
+                    Some(name) => Pack::open(name, &config)?,
+                    None => query_pack(action.pack_action.clone(), &config)?,
+                };
+                install_launcher_profile(&mut pack, &api_client, &config, mc.as_ref(), *force)?;
             }
-            PackAction::Modify => pack_modification_loop(&api_client, &config),
-            PackAction::Install => {
+            PackAction::Install {
+                pack,
+                mc,
+                side,
+                force,
+                ..
+            } => {
                 if config.install_path.is_some() {
-                    let mut pack = query_pack(PackAction::Install, &config);
-                    pack.install(&api_client, &config);
+                    let mut pack = match pack {
+                        Some(name) => Pack::open(name, &config)?,
+                        None => query_pack(action.pack_action.clone(), &config)?,
+                    };
+                    pack.install(&api_client, &config, mc.as_ref(), *side, *force)?;
                 } else {
-                    eprintln!("No install path given")
+                    return Err(MapitoError::Config("No install path given".to_string()));
                 }
             }
+            PackAction::Export {
+                server,
+                format,
+                dir,
+                zip,
+                mc,
+            } => {
+                let mut pack = query_pack(action.pack_action.clone(), &config)?;
+                match (server, format, dir) {
+                    (Some(server), _, _) => {
+                        export_server(&mut pack, server, &api_client, &config, mc.as_ref())?
+                    }
+                    (None, Some(ExportFormat::Packwiz), Some(dir)) => export_packwiz(
+                        &pack,
+                        dir,
+                        &MetaClient::new(&config)?,
+                        &config,
+                        mc.as_ref(),
+                    )?,
+                    (None, Some(ExportFormat::Prism), Some(dir)) => export_prism(
+                        &mut pack,
+                        dir,
+                        &api_client,
+                        &MetaClient::new(&config)?,
+                        &config,
+                        mc.as_ref(),
+                    )?,
+                    _ => unreachable!("clap requires --server or a --format with a directory"),
+                }
+                if let (true, Some(dir)) = (zip, dir) {
+                    zip_export(dir)?;
+                }
+            }
+            PackAction::PinLoader { version } => {
+                let mut pack = query_pack(action.pack_action.clone(), &config)?;
+                pin_loader(
+                    &api_client,
+                    &MetaClient::new(&config)?,
+                    &mut pack,
+                    version.clone(),
+                    &config,
+                )?;
+            }
+            PackAction::Import { format, dir } => match format {
+                PackFormat::Packwiz => import_packwiz(dir, &api_client, &config)?,
+            },
             PackAction::Remove => {
-                let pack = query_pack(PackAction::Remove, &config);
-                pack.remove(&config);
+                let pack = query_pack(PackAction::Remove, &config)?;
+                pack.remove(&config)?;
             }
             PackAction::List => {
-                list_packs(config);
+                list_packs(config)?;
             }
         },
+        Some(Commands::Instance(action)) => match &action.instance_action {
+            InstanceAction::List => list_instances(&config)?,
+        },
         Some(Commands::Config { info }) => {
             if *info {
                 println!("{}", config);
             } else {
-                eprintln!(
-                    "Could not Start Editor: {}",
-                    Command::new(var("EDITOR").unwrap_or("nano".to_string()))
-                        .args(config_path())
-                        .exec()
+                let err = Command::new(var("EDITOR").unwrap_or("nano".to_string()))
+                    .arg(config_path()?)
+                    .exec();
+                return Err(
+                    io::Error::new(err.kind(), format!("could not start editor: {err}")).into(),
                 );
             }
         }
         None => (),
     }
+    Ok(())
 }
 
-fn pack_creation_loop(client: &ApiClient, config: &Configuration) {
+fn pack_creation_loop(client: &ApiClient, config: &Configuration) -> Result<(), MapitoError> {
     let mut version_desc = MVDescriptor {
-        mc_ver: MCVersion::new(),
+        mc_ver: MCVersion::latest(),
+        mc_range: None,
+        targets: Vec::new(),
         version_types: vec![VT::Release],
         loader: Loader::Fabric,
+        loader_version: None,
+        accepted_loaders: Vec::new(),
     };
 
     let abort_msg = "Aborting pack creation.";
 
     println!("Please enter the Name of the new Pack:");
     let name = read_line_to_string();
-    version_desc.mc_ver = match prompt_for("Please enter the Minecraft version of this pack") {
-        Some(ver) => ver,
-        None => {
-            println!("{abort_msg}");
-            return;
-        }
-    };
+    version_desc.mc_ver =
+        match prompt_mc_version(client, "Please enter the Minecraft version of this pack") {
+            Some(ver) => ver,
+            None => {
+                println!("{abort_msg}");
+                return Ok(());
+            }
+        };
+    version_desc.targets = prompt_mc_versions(
+        client,
+        "Enter further Minecraft versions the pack should be locked for",
+    );
+    version_desc
+        .targets
+        .retain(|target| target != &version_desc.mc_ver);
     version_desc.loader = match list_select("Select a Modloader", LOADERS) {
         Some(loader) => loader,
         None => {
             println!("{abort_msg}");
-            return;
+            return Ok(());
         }
     };
     version_desc.version_types =
@@ -230,41 +388,42 @@ fn pack_creation_loop(client: &ApiClient, config: &Configuration) {
             Some(vt) => vt,
             None => {
                 println!("{abort_msg}");
-                return;
+                return Ok(());
             }
         };
-    println!("Please confirm your input:\n Pack Name: {name}\n Minecraft version: {}\n Mod Loader: {}\n version types: {}",
+    println!("Please confirm your input:\n Pack Name: {name}\n Minecraft version: {}{}\n Mod Loader: {}\n version types: {}",
         version_desc.mc_ver,
+        version_desc.targets.iter().map(|target| format!(" +{target}")).collect::<String>(),
         version_desc.loader,
         version_desc.version_types.iter().map(|vt| vt.to_string() + " ").collect::<String>());
     if !confirm_input() {
         println!("Aborting pack Creation");
-        return;
+        return Ok(());
     }
     println!(
         "Now you can search for mods and add them to the pack, you can finish by entering 'q'"
     );
     let mods: Vec<String> = search_mods(client, Some(&version_desc));
 
-    create_pack(client, name, version_desc, &mods, config);
+    create_pack(client, name, version_desc, &mods, config)
 }
 
-fn pack_modification_loop(client: &ApiClient, config: &Configuration) {
-    let mut pack = query_pack(PackAction::Modify, config);
+fn pack_modification_loop(client: &ApiClient, config: &Configuration) -> Result<(), MapitoError> {
+    let mut pack = query_pack(PackAction::Modify, config)?;
     loop {
         println!("{}", pack,);
-        match prompt_for::<char>("choose a category to modify:\n0 - Name\n1 - Version Info\n\tMinecraft Version\n\tVersion Types\n\tLoader\n2 - Mods\n") {
+        match prompt_for::<char>("choose a category to modify:\n0 - Name\n1 - Version Info\n\tMinecraft Version\n\tVersion Types\n\tLoader\n\tAccepted Minecraft Versions\n\tFurther Target Versions\n\tAccepted Loaders\n2 - Mods\n") {
             Some('0') => {
-                pack.remove(config);
                 match prompt_for::<String>("Enter a new name for the Pack.") {
-                    Some(name) => pack.name = name,
+                    Some(name) => pack.rename(name, config)?,
                     None => println!("Name not changed."),
                 };
-                pack.save(config);
-                return;
+                return Ok(());
             }
             Some('1') => {
                 let true_name = pack.name.clone();
+                // keep the pack locked while its changes go through the temporary copy
+                let _true_lock = FileLock::acquire(&Pack::file_path(&true_name, config))?;
                 pack.name += "_tmp";
                 loop {
                     println!("What do you want to change?");
@@ -277,11 +436,41 @@ fn pack_modification_loop(client: &ApiClient, config: &Configuration) {
                             .map(|vt| vt.to_string() + " ")
                             .collect::<String>()
                     );
-                    println!("  2 - Loader: {}", pack.version_info.loader);
+                    println!(
+                        "  2 - Loader: {} {}",
+                        pack.version_info.loader,
+                        pack.version_info.loader_version.as_deref().unwrap_or_default()
+                    );
+                    println!(
+                        "  3 - Accepted Minecraft Versions: {}",
+                        pack.version_info
+                            .mc_range
+                            .as_ref()
+                            .map_or(pack.version_info.mc_ver.to_string(), |r| r.to_string())
+                    );
+                    println!(
+                        "  4 - Further Target Versions: {}",
+                        pack.version_info
+                            .targets
+                            .iter()
+                            .map(|target| target.to_string() + " ")
+                            .collect::<String>()
+                    );
+                    println!(
+                        "  5 - Accepted Loaders: {}",
+                        pack.version_info
+                            .mod_loaders()
+                            .iter()
+                            .map(|loader| loader.to_string() + " ")
+                            .collect::<String>()
+                    );
                     match prompt_for::<char>("") {
                         Some('0') => {
-                            match prompt_for::<MCVersion>("enter a new Minecraft version for the Pack.") {
-                                Some(ver) => {pack.version_info.mc_ver = ver},
+                            match prompt_mc_version(client, "enter a new Minecraft version for the Pack.") {
+                                Some(ver) => {
+                                    pack.version_info.mc_ver = ver;
+                                    pack.version_info.loader_version = None;
+                                }
                                 None => {
                                     println!("Version not changed.");
                                 },
@@ -295,63 +484,130 @@ fn pack_modification_loop(client: &ApiClient, config: &Configuration) {
                             };
                         }
                         Some('2') => {
-                            match list_select(
-                                "Please enter the loader you want to change to",
-                                &[
-                                    Loader::Fabric,
-                                    Loader::Quilt,
-                                    Loader::Neoforge,
-                                    Loader::Forge
-                                ]
-                            ) {
-                                Some(loader) => pack.version_info.loader = loader,
+                            match list_select("Please enter the loader you want to change to", LOADERS) {
+                                Some(loader) => {
+                                    pack.version_info.loader = loader;
+                                    pack.version_info.loader_version = None;
+                                }
                                 None => println!("Loader not changed."),
                             };
                         }
+                        Some('3') => {
+                            match prompt_for::<MCVersionRange>("enter the Minecraft versions mods may be built for, e.g. '>=1.20.1, <1.21' or '1.20.x'") {
+                                Some(range) => pack.version_info.mc_range = Some(range),
+                                None => println!("Version range not changed."),
+                            };
+                        }
+                        Some('4') => {
+                            let mut targets = prompt_mc_versions(client, "enter all further Minecraft versions the Pack should be locked for");
+                            targets.retain(|target| target != &pack.version_info.mc_ver);
+                            pack.version_info.targets = targets;
+                        }
+                        Some('5') => {
+                            match prompt_for::<String>("enter the loaders whose builds may be used when there is none for the pack loader, in order of preference and separated by ','. Leave empty for the defaults") {
+                                Some(loaders) => match loaders
+                                    .split(',')
+                                    .map(str::trim)
+                                    .filter(|loader| !loader.is_empty())
+                                    .map(str::parse)
+                                    .collect::<Result<Vec<Loader>, String>>()
+                                {
+                                    Ok(loaders) => pack.version_info.accepted_loaders = loaders,
+                                    Err(e) => println!("{e}, accepted loaders not changed."),
+                                },
+                                None => println!("Accepted loaders not changed."),
+                            };
+                        }
                         None => break,
                         _ => println!("unexpected input"),
                     }
                 }
-                pack.save(config);
+                pack.save(config)?;
                 println!("updating mods.");
                 match update_pack(client, pack.name.clone(), config) {
                     Ok(_) => {
-                        pack = Pack::open(&pack.name, config);
-                        pack.remove(config);
+                        pack = Pack::open(&pack.name, config)?;
+                        pack.remove(config)?;
                         pack.name = true_name;
-                        pack.save(config);
+                        pack.save(config)?;
                     }
-                    Err(_) => {
-                        pack.remove(config);
+                    Err(e) => {
+                        eprintln!("Could not update mods, keeping previous version info: {e}");
+                        pack.remove(config)?;
                         pack.name = true_name;
                     }
                 };
-                pack = Pack::open(&pack.name, config);
+                pack = Pack::open(&pack.name, config)?;
             }
             Some('2') => loop {
                 pack.list_mods();
                 println!("Choose an Action:");
                 println!("  0 - add mods");
                 println!("  1 - remove a mod");
+                println!("  2 - add a file from a url");
+                println!("  3 - add a local file");
+                println!("  4 - add a github release");
                 match prompt_for::<char>("") {
                     Some('0') => {
                         let mods = search_mods(client,  Some(&pack.version_info));
-                        for item in mods {
-                            pack.add_mod(&item, client);
+                        for (item, added) in pack.add_mods(&mods, client)? {
+                            if let Err(e) = added {
+                                eprintln!("Could not add '{item}': {e}");
+                            }
                         }
-                        pack.save(config);
+                        pack.save(config)?;
                     }
                     Some('1') => {
                         println!("Enter which mod to remove:");
                         pack.mods.remove(&read_line_to_string());
-                        pack.save(config);
-                        pack = Pack::open(&pack.name, config);
+                        pack.save(config)?;
+                        pack = Pack::open(&pack.name, config)?;
+                    }
+                    Some('2') => {
+                        let Some(key) = prompt_for::<String>("Enter a name for the entry") else {
+                            continue;
+                        };
+                        let Some(url) = prompt_for::<String>("Enter the download url") else {
+                            continue;
+                        };
+                        let Some(sha512) = prompt_for::<String>("Enter the sha512 of the file") else {
+                            continue;
+                        };
+                        pack.add_url(&key, url, sha512)?;
+                        pack.save(config)?;
+                    }
+                    Some('3') => {
+                        let Some(key) = prompt_for::<String>("Enter a name for the entry") else {
+                            continue;
+                        };
+                        let Some(path) = prompt_for::<String>("Enter the path of the file, relative paths start at the pack path") else {
+                            continue;
+                        };
+                        match pack.add_local(&key, path, config) {
+                            Ok(()) => pack.save(config)?,
+                            Err(e) => eprintln!("Could not add '{key}': {e}"),
+                        }
+                    }
+                    Some('4') => {
+                        let Some(key) = prompt_for::<String>("Enter a name for the entry") else {
+                            continue;
+                        };
+                        let Some(repo) = prompt_for::<String>("Enter the github repository as owner/name") else {
+                            continue;
+                        };
+                        let Some(asset) = prompt_for::<String>("Enter a regex the release asset name has to match, e.g. '-fabric\\.jar$'") else {
+                            continue;
+                        };
+                        match pack.add_github(&key, repo, asset, &GithubClient::new(config)?) {
+                            Ok(()) => pack.save(config)?,
+                            Err(e) => eprintln!("Could not add '{key}': {e}"),
+                        }
                     }
                     None => break,
                     _ => println!("unexpected input"),
                 }
             },
-            None => return,
+            None => return Ok(()),
             _ => println!("unexpected input"),
         }
     }
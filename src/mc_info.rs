@@ -1,27 +1,34 @@
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, str::FromStr, sync::LazyLock};
 
+use clap::ValueEnum;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::mrapi::defines::Version;
+use crate::mrapi::defines::{Project, Version};
 
-/// Const list of all available mod launchers
-pub const LOADERS: &[Loader; 4] = &[
+/// Const list of all available mod launchers and server platforms a pack can be built for
+pub const LOADERS: &[Loader; 16] = &[
     Loader::Fabric,
     Loader::Quilt,
     Loader::Neoforge,
     Loader::Forge,
+    Loader::Liteloader,
+    Loader::Modloader,
+    Loader::Rift,
+    Loader::Paper,
+    Loader::Spigot,
+    Loader::Bukkit,
+    Loader::Purpur,
+    Loader::Folia,
+    Loader::Sponge,
+    Loader::Velocity,
+    Loader::Bungeecord,
+    Loader::Waterfall,
 ];
 
-/// Provide functionality for interacting with minecraft versions
-pub trait MCVersionUtils {
-    /// check if the version is set to be the latest version.
-    fn is_latest(&self) -> bool;
-    /// set the version to get the latest version
-    fn latest() -> Self;
-}
-
-#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
 pub enum VT {
+    #[default]
     Release,
     Beta,
     Alpha,
@@ -63,12 +70,71 @@ impl FromStr for VT {
     }
 }
 
-#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Loader {
     Fabric,
     Quilt,
     Neoforge,
     Forge,
+    Liteloader,
+    Modloader,
+    Rift,
+    Paper,
+    Spigot,
+    Bukkit,
+    Purpur,
+    Folia,
+    Sponge,
+    Velocity,
+    Bungeecord,
+    Waterfall,
+    /// vanilla resource packs
+    Minecraft,
+    Iris,
+    Optifine,
+    Canvas,
+    Datapack,
+    /// any loader mapito does not know yet
+    Other(String),
+}
+
+impl Loader {
+    /// loaders whose builds also run on this one, in the order they should be tried
+    pub fn compatible_loaders(&self) -> Vec<Loader> {
+        match self {
+            Self::Quilt => vec![Self::Fabric],
+            Self::Spigot => vec![Self::Bukkit],
+            Self::Paper => vec![Self::Spigot, Self::Bukkit],
+            Self::Purpur => vec![Self::Paper, Self::Spigot, Self::Bukkit],
+            Self::Waterfall => vec![Self::Bungeecord],
+            _ => Vec::new(),
+        }
+    }
+
+    /// server platforms whose projects are plugins instead of mods
+    pub fn is_plugin_platform(&self) -> bool {
+        matches!(
+            self,
+            Self::Paper
+                | Self::Spigot
+                | Self::Bukkit
+                | Self::Purpur
+                | Self::Folia
+                | Self::Sponge
+                | Self::Velocity
+                | Self::Bungeecord
+                | Self::Waterfall
+        )
+    }
+}
+
+impl Serialize for Loader {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
 }
 
 impl<'de> Deserialize<'de> for Loader {
@@ -90,6 +156,24 @@ impl Display for Loader {
             Self::Quilt => "quilt",
             Self::Neoforge => "neoforge",
             Self::Forge => "forge",
+            Self::Liteloader => "liteloader",
+            Self::Modloader => "modloader",
+            Self::Rift => "rift",
+            Self::Paper => "paper",
+            Self::Spigot => "spigot",
+            Self::Bukkit => "bukkit",
+            Self::Purpur => "purpur",
+            Self::Folia => "folia",
+            Self::Sponge => "sponge",
+            Self::Velocity => "velocity",
+            Self::Bungeecord => "bungeecord",
+            Self::Waterfall => "waterfall",
+            Self::Minecraft => "minecraft",
+            Self::Iris => "iris",
+            Self::Optifine => "optifine",
+            Self::Canvas => "canvas",
+            Self::Datapack => "datapack",
+            Self::Other(loader) => loader,
         };
         write!(f, "{}", to_write)
     }
@@ -103,7 +187,176 @@ impl FromStr for Loader {
             "neoforge" => Ok(Self::Neoforge),
             "quilt" => Ok(Self::Quilt),
             "forge" => Ok(Self::Forge),
-            _ => Err("Unknown Modloader".to_string()),
+            "liteloader" => Ok(Self::Liteloader),
+            "modloader" => Ok(Self::Modloader),
+            "rift" => Ok(Self::Rift),
+            "paper" => Ok(Self::Paper),
+            "spigot" => Ok(Self::Spigot),
+            "bukkit" => Ok(Self::Bukkit),
+            "purpur" => Ok(Self::Purpur),
+            "folia" => Ok(Self::Folia),
+            "sponge" => Ok(Self::Sponge),
+            "velocity" => Ok(Self::Velocity),
+            "bungeecord" => Ok(Self::Bungeecord),
+            "waterfall" => Ok(Self::Waterfall),
+            "minecraft" => Ok(Self::Minecraft),
+            "iris" => Ok(Self::Iris),
+            "optifine" => Ok(Self::Optifine),
+            "canvas" => Ok(Self::Canvas),
+            "datapack" => Ok(Self::Datapack),
+            _ => Err(format!("unknown loader '{s}'")),
+        }
+    }
+}
+
+impl Loader {
+    /// Like [`Loader::from_str`], but loaders mapito does not know yet are kept as
+    /// [`Loader::Other`] instead of being rejected, for loaders listed by modrinth.
+    pub fn from_api(s: &str) -> Self {
+        s.parse().unwrap_or_else(|_| Self::Other(s.to_lowercase()))
+    }
+}
+
+/// serde `deserialize_with` for the loaders modrinth lists, see [`Loader::from_api`]
+pub fn deserialize_api_loaders<'de, D>(deserializer: D) -> Result<Vec<Loader>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|loader| Loader::from_api(loader))
+        .collect())
+}
+
+/// serde `deserialize_with` for a loader taken from modrinth, see [`Loader::from_api`]
+pub fn deserialize_api_loader<'de, D>(deserializer: D) -> Result<Option<Loader>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.map(|loader| Loader::from_api(&loader)))
+}
+
+/// The side of the game a pack is installed for
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Side {
+    Client,
+    Server,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_write = match self {
+            Self::Client => "client",
+            Self::Server => "server",
+        };
+        write!(f, "{}", to_write)
+    }
+}
+
+/// Whether a project is needed on one side of the game, as reported by modrinth
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SideSupport {
+    Required,
+    Optional,
+    Unsupported,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl SideSupport {
+    pub fn is_unknown(&self) -> bool {
+        *self == Self::Unknown
+    }
+}
+
+/// The kind of content a pack entry installs
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+pub enum ProjectType {
+    #[default]
+    Mod,
+    /// a plugin for a server platform like paper or velocity
+    Plugin,
+    ResourcePack,
+    ShaderPack,
+    DataPack,
+}
+
+impl ProjectType {
+    /// content type of a modrinth project when used with `loader`,
+    /// datapacks are published as mods that only support the datapack loader and
+    /// mods used on a server platform are plugins.
+    pub fn of_project(project: &Project, loader: &Loader) -> Self {
+        match project.project_type.as_str() {
+            "resourcepack" => Self::ResourcePack,
+            "shader" => Self::ShaderPack,
+            "datapack" => Self::DataPack,
+            "plugin" => Self::Plugin,
+            _ if project.loaders == [Loader::Datapack] => Self::DataPack,
+            _ if loader.is_plugin_platform() => Self::Plugin,
+            _ => Self::Mod,
+        }
+    }
+
+    /// loaders a file of this type may be built for, starting with the preferred one.
+    /// Mods and plugins need one of the loaders accepted by `version_desc`.
+    pub fn loaders(&self, version_desc: &MVDescriptor) -> Vec<Loader> {
+        match self {
+            Self::Mod | Self::Plugin => version_desc.mod_loaders(),
+            Self::ResourcePack => vec![Loader::Minecraft],
+            Self::ShaderPack => vec![Loader::Iris, Loader::Optifine, Loader::Canvas],
+            Self::DataPack => vec![Loader::Datapack],
+        }
+    }
+
+    /// directory below the instance root files of this type are installed to
+    pub fn install_dir(&self) -> &'static str {
+        match self {
+            Self::Mod => "mods",
+            Self::Plugin => "plugins",
+            Self::ResourcePack => "resourcepacks",
+            Self::ShaderPack => "shaderpacks",
+            Self::DataPack => "datapacks",
+        }
+    }
+}
+
+impl Display for ProjectType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_write = match self {
+            Self::Mod => "mod",
+            Self::Plugin => "plugin",
+            Self::ResourcePack => "resourcepack",
+            Self::ShaderPack => "shader",
+            Self::DataPack => "datapack",
+        };
+        write!(f, "{}", to_write)
+    }
+}
+
+impl FromStr for ProjectType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mod" => Ok(Self::Mod),
+            "plugin" => Ok(Self::Plugin),
+            "resourcepack" => Ok(Self::ResourcePack),
+            "shader" | "shaderpack" => Ok(Self::ShaderPack),
+            "datapack" => Ok(Self::DataPack),
+            _ => Err("Unknown project type".to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProjectType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Self::from_str(&String::deserialize(deserializer)?) {
+            Ok(project_type) => Ok(project_type),
+            Err(e) => Err(serde::de::Error::custom(e)),
         }
     }
 }
@@ -111,34 +364,680 @@ impl FromStr for Loader {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MVDescriptor {
     pub mc_ver: MCVersion,
+    /// Minecraft versions mods may be built for, defaults to exactly `mc_ver`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_range: Option<MCVersionRange>,
+    /// Further Minecraft versions the pack is locked for next to `mc_ver`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<MCVersion>,
     pub version_types: Vec<VT>,
     pub loader: Loader,
+    /// Version of `loader` the pack is played with, e.g. a Fabric loader or NeoForge version.
+    /// Only applies to `mc_ver`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    /// Loaders whose builds are used when there is no build for `loader`, in order of
+    /// preference. Defaults to [`Loader::compatible_loaders`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accepted_loaders: Vec<Loader>,
 }
 
 impl PartialEq for MVDescriptor {
     fn eq(&self, other: &Self) -> bool {
         self.mc_ver == other.mc_ver
+            && self.mc_range == other.mc_range
+            && self.targets == other.targets
             && self.version_types == other.version_types
             && self.loader == other.loader
+            && self.loader_version == other.loader_version
+            && self.accepted_loaders == other.accepted_loaders
     }
 }
 
 impl MVDescriptor {
-    pub fn check_version_compat(&self, version: &Version) -> bool {
-        version.game_versions.contains(&self.mc_ver)
-            && version.loaders.contains(&self.loader)
+    /// check if a project version of the given type can be used with this descriptor,
+    /// a "latest" Minecraft version accepts any game version.
+    pub fn check_version_compat(&self, version: &Version, project_type: ProjectType) -> bool {
+        self.accepts_game_versions(&version.game_versions)
+            && self.resolved_loader(version, project_type).is_some()
             && self.version_types.contains(&version.version_type)
     }
+
+    /// check if a project built for the given Minecraft versions fits this descriptor
+    pub fn accepts_game_versions(&self, game_versions: &[MCVersion]) -> bool {
+        match &self.mc_range {
+            Some(range) => game_versions.iter().any(|v| range.matches(v)),
+            None => self.mc_ver.is_latest() || game_versions.contains(&self.mc_ver),
+        }
+    }
+
+    /// all Minecraft versions this descriptor targets, starting with `mc_ver`
+    pub fn all_targets(&self) -> impl Iterator<Item = &MCVersion> {
+        std::iter::once(&self.mc_ver).chain(&self.targets)
+    }
+
+    /// descriptor for a single target Minecraft version,
+    /// the version range only applies to the main target.
+    pub fn for_target(&self, target: &MCVersion) -> MVDescriptor {
+        MVDescriptor {
+            mc_ver: target.clone(),
+            mc_range: match target == &self.mc_ver {
+                true => self.mc_range.clone(),
+                false => None,
+            },
+            targets: Vec::new(),
+            version_types: self.version_types.clone(),
+            loader: self.loader.clone(),
+            loader_version: match target == &self.mc_ver {
+                true => self.loader_version.clone(),
+                false => None,
+            },
+            accepted_loaders: self.accepted_loaders.clone(),
+        }
+    }
+
+    /// the loader of the pack followed by the loaders it accepts builds for
+    pub fn mod_loaders(&self) -> Vec<Loader> {
+        let fallbacks = match self.accepted_loaders.is_empty() {
+            true => self.loader.compatible_loaders(),
+            false => self.accepted_loaders.clone(),
+        };
+        let mut loaders = vec![self.loader.clone()];
+        for loader in fallbacks {
+            if !loaders.contains(&loader) {
+                loaders.push(loader);
+            }
+        }
+        loaders
+    }
+
+    /// the most preferred loader a project version of the given type was built for
+    pub fn resolved_loader(&self, version: &Version, project_type: ProjectType) -> Option<Loader> {
+        project_type
+            .loaders(self)
+            .into_iter()
+            .find(|loader| version.loaders.contains(loader))
+    }
+}
+
+/// weekly snapshots like `23w13a`
+static WEEKLY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{2})w(\d{2})([a-z~]+)$").expect("valid regex"));
+/// releases, pre-releases, release candidates and numbered snapshots
+static NUMBERED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(\d+(?:\.\d+)+)(?:(?:-| )(pre|Pre-Release |rc|Release Candidate |snapshot-)(\d+))?$",
+    )
+    .expect("valid regex")
+});
+
+/// Development stage of a numbered Minecraft version, ordered from earliest to final release.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    Snapshot(u32),
+    PreRelease(u32),
+    ReleaseCandidate(u32),
+    Release,
+}
+
+#[derive(Debug, Clone)]
+enum VersionKind {
+    Latest,
+    LatestSnapshot,
+    /// A numbered version like `1.20.1`, `1.21-pre2`, `1.20.5-rc1` or `26.1-snapshot-3`.
+    /// Trailing zeros are dropped from `numbers` so that `1.20` and `1.20.0` compare equal.
+    Numbered {
+        numbers: Vec<u32>,
+        stage: Stage,
+    },
+    /// A weekly snapshot like `23w13a`.
+    Weekly {
+        year: u32,
+        week: u32,
+        id: String,
+    },
+    /// Anything we do not understand, e.g. april fools versions. Only equal to itself.
+    Other,
+}
+
+/// A Minecraft version as it is used by modrinth.
+///
+/// Releases, pre-releases and release candidates are ordered among each other, as are weekly
+/// snapshots. Weekly snapshots cannot be ordered against numbered versions.
+#[derive(Debug, Clone)]
+pub struct MCVersion {
+    raw: String,
+    kind: VersionKind,
+}
+
+impl MCVersion {
+    /// the `[major, minor, patch, ...]` numbers of a numbered version
+    fn numbers(&self) -> Option<&[u32]> {
+        match &self.kind {
+            VersionKind::Numbered { numbers, .. } => Some(numbers),
+            _ => None,
+        }
+    }
+
+    /// set the version to get the latest version
+    pub fn latest() -> Self {
+        MCVersion {
+            raw: "latest".to_string(),
+            kind: VersionKind::Latest,
+        }
+    }
+
+    /// check if the version is set to be the latest (snapshot) version
+    /// instead of a concrete one.
+    pub fn is_latest(&self) -> bool {
+        matches!(self.kind, VersionKind::Latest | VersionKind::LatestSnapshot)
+    }
+
+    /// check if the version is set to be the latest version including snapshots.
+    pub fn is_latest_snapshot(&self) -> bool {
+        matches!(self.kind, VersionKind::LatestSnapshot)
+    }
+
+    /// check if this is a full release, not a snapshot or pre-release
+    pub fn is_release(&self) -> bool {
+        matches!(
+            self.kind,
+            VersionKind::Numbered {
+                stage: Stage::Release,
+                ..
+            }
+        )
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// one spelling for all equal versions, e.g. `1.20` for `1.20.0` and `1.21-pre1` for
+    /// `1.21 Pre-Release 1`
+    pub fn normalized(&self) -> String {
+        let VersionKind::Numbered { numbers, stage } = &self.kind else {
+            return self.raw.clone();
+        };
+        let mut normalized = numbers
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<String>>()
+            .join(".");
+        if numbers.len() == 1 {
+            normalized += ".0";
+        }
+        match stage {
+            Stage::Snapshot(n) => normalized + &format!("-snapshot-{n}"),
+            Stage::PreRelease(n) => normalized + &format!("-pre{n}"),
+            Stage::ReleaseCandidate(n) => normalized + &format!("-rc{n}"),
+            Stage::Release => normalized,
+        }
+    }
+}
+
+impl FromStr for MCVersion {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        if raw.is_empty() {
+            return Err("empty Minecraft version".to_string());
+        }
+        let kind = if raw == "latest" {
+            VersionKind::Latest
+        } else if raw == "latest-snapshot" {
+            VersionKind::LatestSnapshot
+        } else if let Some(caps) = WEEKLY.captures(raw) {
+            VersionKind::Weekly {
+                year: caps[1].parse().expect("two digits"),
+                week: caps[2].parse().expect("two digits"),
+                id: caps[3].to_string(),
+            }
+        } else if let Some(caps) = NUMBERED.captures(raw) {
+            let parse = |n: &str| {
+                n.parse::<u32>()
+                    .map_err(|_| format!("version number {n} in '{raw}' is too large"))
+            };
+            let mut numbers: Vec<u32> = caps[1].split('.').map(parse).collect::<Result<_, _>>()?;
+            while numbers.len() > 1 && numbers.last() == Some(&0) {
+                numbers.pop();
+            }
+            let n = caps.get(3).map_or(Ok(0), |n| parse(n.as_str()))?;
+            let stage = match caps.get(2).map(|m| m.as_str()) {
+                None => Stage::Release,
+                Some("pre") | Some("Pre-Release ") => Stage::PreRelease(n),
+                Some("rc") | Some("Release Candidate ") => Stage::ReleaseCandidate(n),
+                Some(_) => Stage::Snapshot(n),
+            };
+            VersionKind::Numbered { numbers, stage }
+        } else {
+            VersionKind::Other
+        };
+
+        Ok(MCVersion {
+            raw: raw.to_string(),
+            kind,
+        })
+    }
+}
+
+impl PartialEq for MCVersion {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.kind, &other.kind) {
+            (VersionKind::Latest, VersionKind::Latest) => true,
+            (VersionKind::LatestSnapshot, VersionKind::LatestSnapshot) => true,
+            (
+                VersionKind::Numbered { numbers, stage },
+                VersionKind::Numbered {
+                    numbers: o_numbers,
+                    stage: o_stage,
+                },
+            ) => numbers == o_numbers && stage == o_stage,
+            (
+                VersionKind::Weekly { year, week, id },
+                VersionKind::Weekly {
+                    year: o_year,
+                    week: o_week,
+                    id: o_id,
+                },
+            ) => year == o_year && week == o_week && id == o_id,
+            (VersionKind::Other, VersionKind::Other) => self.raw == other.raw,
+            _ => false,
+        }
+    }
+}
+
+impl PartialOrd for MCVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (&self.kind, &other.kind) {
+            (
+                VersionKind::Numbered { numbers, stage },
+                VersionKind::Numbered {
+                    numbers: o_numbers,
+                    stage: o_stage,
+                },
+            ) => Some(numbers.cmp(o_numbers).then(stage.cmp(o_stage))),
+            (
+                VersionKind::Weekly { year, week, id },
+                VersionKind::Weekly {
+                    year: o_year,
+                    week: o_week,
+                    id: o_id,
+                },
+            ) => Some((year, week, id).cmp(&(o_year, o_week, o_id))),
+            _ if self == other => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MCVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Serialize for MCVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for MCVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Self::from_str(&String::deserialize(deserializer)?) {
+            Ok(ver) => Ok(ver),
+            Err(e) => Err(serde::de::Error::custom(e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Comparator {
+    Eq(MCVersion),
+    Gt(MCVersion),
+    Ge(MCVersion),
+    Lt(MCVersion),
+    Le(MCVersion),
+    /// `1.20.x` matches every numbered version starting with the given numbers
+    Wildcard(Vec<u32>),
+}
+
+impl Comparator {
+    fn matches(&self, version: &MCVersion) -> bool {
+        match self {
+            Comparator::Eq(v) => version == v,
+            Comparator::Gt(v) => version > v,
+            Comparator::Ge(v) => version >= v,
+            Comparator::Lt(v) => version < v,
+            Comparator::Le(v) => version <= v,
+            Comparator::Wildcard(prefix) => version.numbers().is_some_and(|numbers| {
+                prefix
+                    .iter()
+                    .enumerate()
+                    .all(|(i, n)| numbers.get(i).unwrap_or(&0) == n)
+            }),
+        }
+    }
+}
+
+/// A set of Minecraft versions, written as comma separated comparators that all have to match,
+/// e.g. `>=1.20.1, <1.21` or `1.20.x`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MCVersionRange {
+    raw: String,
+    comparators: Vec<Comparator>,
+}
+
+impl MCVersionRange {
+    /// check if a version lies within this range
+    pub fn matches(&self, version: &MCVersion) -> bool {
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+impl FromStr for MCVersionRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparators = Vec::new();
+        for part in s.split(',').map(str::trim) {
+            if let Some(prefix) = part.strip_suffix(".x").or_else(|| part.strip_suffix(".*")) {
+                let numbers = prefix
+                    .split('.')
+                    .map(|n| n.parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .map_err(|_| format!("invalid version wildcard '{part}'"))?;
+                comparators.push(Comparator::Wildcard(numbers));
+                continue;
+            }
+            let (op, ver): (fn(MCVersion) -> Comparator, &str) =
+                if let Some(ver) = part.strip_prefix(">=") {
+                    (Comparator::Ge, ver)
+                } else if let Some(ver) = part.strip_prefix("<=") {
+                    (Comparator::Le, ver)
+                } else if let Some(ver) = part.strip_prefix('>') {
+                    (Comparator::Gt, ver)
+                } else if let Some(ver) = part.strip_prefix('<') {
+                    (Comparator::Lt, ver)
+                } else {
+                    (Comparator::Eq, part.strip_prefix('=').unwrap_or(part))
+                };
+            let ver = MCVersion::from_str(ver)?;
+            if matches!(ver.kind, VersionKind::Other) || ver.is_latest() {
+                return Err(format!("invalid Minecraft version '{ver}' in range"));
+            }
+            comparators.push(op(ver));
+        }
+        Ok(MCVersionRange {
+            raw: s.trim().to_string(),
+            comparators,
+        })
+    }
+}
+
+impl Display for MCVersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Serialize for MCVersionRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw)
+    }
 }
 
-pub type MCVersion = String;
+impl<'de> Deserialize<'de> for MCVersionRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Self::from_str(&String::deserialize(deserializer)?) {
+            Ok(range) => Ok(range),
+            Err(e) => Err(serde::de::Error::custom(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ver(s: &str) -> MCVersion {
+        s.parse().expect("valid version")
+    }
+
+    fn range(s: &str) -> MCVersionRange {
+        s.parse().expect("valid range")
+    }
+
+    #[test]
+    fn stages_order_before_the_release() {
+        assert!(ver("1.20-pre1") < ver("1.20-pre2"));
+        assert!(ver("1.20-pre2") < ver("1.20-rc1"));
+        assert!(ver("1.20-rc1") < ver("1.20"));
+        assert!(ver("1.20 Pre-Release 1") == ver("1.20-pre1"));
+        assert!(ver("26.1-snapshot-3") < ver("26.1-pre1"));
+        assert!(ver("1.20") < ver("1.20.1-pre1"));
+        assert!(ver("1.9") < ver("1.10"));
+    }
+
+    #[test]
+    fn trailing_zeros_are_equal() {
+        assert_eq!(ver("1.20"), ver("1.20.0"));
+        assert_eq!(
+            ver("1.20").partial_cmp(&ver("1.20.0")),
+            Some(Ordering::Equal)
+        );
+        assert_ne!(ver("1.20"), ver("1.20.1"));
+        assert!(ver("1.20").is_release());
+    }
+
+    #[test]
+    fn normalized_spellings() {
+        assert_eq!(ver("1.20.0").normalized(), "1.20");
+        assert_eq!(ver("1.0.0").normalized(), "1.0");
+        assert_eq!(ver("1.21 Pre-Release 1").normalized(), "1.21-pre1");
+        assert_eq!(ver("1.20.5 Release Candidate 2").normalized(), "1.20.5-rc2");
+        assert_eq!(ver("23w13a").normalized(), "23w13a");
+    }
 
-impl MCVersionUtils for MCVersion {
-    fn is_latest(&self) -> bool {
-        self == "latest"
+    #[test]
+    fn weekly_snapshots_order_among_each_other() {
+        assert!(ver("23w13a") < ver("23w13b"));
+        assert!(ver("23w13b") < ver("23w14a"));
+        assert!(ver("22w45a") < ver("23w03a"));
+        assert!(!ver("23w13a").is_release());
+        assert_eq!(ver("23w13a").partial_cmp(&ver("1.20")), None);
     }
 
-    fn latest() -> Self {
-        "latest".to_string()
+    #[test]
+    fn unknown_versions_only_equal_themselves() {
+        assert_eq!(ver("3D Shareware v1.34"), ver("3D Shareware v1.34"));
+        assert_ne!(ver("3D Shareware v1.34"), ver("1.34"));
+        assert!(ver("latest").is_latest());
+        assert!(ver("latest-snapshot").is_latest_snapshot());
+    }
+
+    #[test]
+    fn bounded_ranges() {
+        let r = range(">=1.20.1, <1.21");
+        assert!(!r.matches(&ver("1.20")));
+        assert!(r.matches(&ver("1.20.1")));
+        assert!(r.matches(&ver("1.20.6")));
+        assert!(!r.matches(&ver("1.21")));
+        assert!(!r.matches(&ver("1.21.1")));
+        assert!(!r.matches(&ver("23w13a")));
+        assert!(range("=1.20").matches(&ver("1.20.0")));
+        assert!(range("<=1.20.1").matches(&ver("1.20.1")));
+        assert!(!range(">1.20.1").matches(&ver("1.20.1")));
+    }
+
+    #[test]
+    fn wildcard_ranges() {
+        let r = range("1.20.x");
+        assert!(r.matches(&ver("1.20")));
+        assert!(r.matches(&ver("1.20.0")));
+        assert!(r.matches(&ver("1.20.6")));
+        assert!(!r.matches(&ver("1.21")));
+        assert!(!r.matches(&ver("1.2")));
+        assert!(!r.matches(&ver("23w13a")));
+        assert!(range("1.*").matches(&ver("1.21.4")));
+    }
+
+    #[test]
+    fn only_api_loaders_fall_back_to_other() {
+        assert_eq!("Fabric".parse::<Loader>(), Ok(Loader::Fabric));
+        assert!("fabirc".parse::<Loader>().is_err());
+        assert!("".parse::<Loader>().is_err());
+        assert_eq!(Loader::from_api("paper"), Loader::Paper);
+        assert_eq!(
+            Loader::from_api("Ornithe"),
+            Loader::Other("ornithe".to_string())
+        );
+        let loaders: Vec<Loader> =
+            deserialize_api_loaders(serde_json::json!(["fabric", "ornithe"])).unwrap();
+        assert_eq!(
+            loaders,
+            vec![Loader::Fabric, Loader::Other("ornithe".to_string())]
+        );
+        assert!(serde_json::from_value::<Loader>(serde_json::json!("ornithe")).is_err());
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!("".parse::<MCVersion>().is_err());
+        assert!("   ".parse::<MCVersion>().is_err());
+        assert!("1.99999999999".parse::<MCVersion>().is_err());
+        assert!("1.20-pre99999999999".parse::<MCVersion>().is_err());
+        for garbage in [
+            "",
+            "foo",
+            ">=",
+            ">=bar, <1.21",
+            "1.a.x",
+            "latest",
+            "<1.21, ",
+        ] {
+            assert!(
+                garbage.parse::<MCVersionRange>().is_err(),
+                "'{garbage}' should be rejected"
+            );
+        }
+    }
+
+    fn descriptor(loader: Loader, accepted_loaders: Vec<Loader>) -> MVDescriptor {
+        MVDescriptor {
+            mc_ver: ver("1.21.5"),
+            mc_range: None,
+            targets: Vec::new(),
+            version_types: vec![VT::Release],
+            loader,
+            loader_version: None,
+            accepted_loaders,
+        }
+    }
+
+    fn built_for(loaders: &[&str]) -> Version {
+        serde_json::from_value(serde_json::json!({
+            "id": "v1",
+            "project_id": "p1",
+            "game_versions": ["1.21.5"],
+            "loaders": loaders,
+            "name": "Mod 1.0",
+            "version_number": "1.0",
+            "downloads": 0,
+            "version_type": "release",
+            "files": [],
+            "dependencies": [],
+        }))
+        .expect("valid version")
+    }
+
+    #[test]
+    fn loaders_fall_back_to_compatible_ones() {
+        use Loader::*;
+        let cases = [
+            (Fabric, vec![], vec![Fabric]),
+            (Quilt, vec![], vec![Quilt, Fabric]),
+            (Spigot, vec![], vec![Spigot, Bukkit]),
+            (Paper, vec![], vec![Paper, Spigot, Bukkit]),
+            (Purpur, vec![], vec![Purpur, Paper, Spigot, Bukkit]),
+            (Waterfall, vec![], vec![Waterfall, Bungeecord]),
+            (Neoforge, vec![], vec![Neoforge]),
+            (Neoforge, vec![Forge], vec![Neoforge, Forge]),
+            (Quilt, vec![Quilt, Forge], vec![Quilt, Forge]),
+        ];
+        for (loader, accepted, expected) in cases {
+            let desc = descriptor(loader.clone(), accepted.clone());
+            assert_eq!(
+                desc.mod_loaders(),
+                expected,
+                "{loader} accepting {accepted:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn native_builds_are_preferred() {
+        use Loader::*;
+        let cases = [
+            (
+                Quilt,
+                ProjectType::Mod,
+                vec!["fabric", "quilt"],
+                Some(Quilt),
+            ),
+            (Quilt, ProjectType::Mod, vec!["fabric"], Some(Fabric)),
+            (Fabric, ProjectType::Mod, vec!["quilt"], None),
+            (
+                Spigot,
+                ProjectType::Plugin,
+                vec!["bukkit", "spigot"],
+                Some(Spigot),
+            ),
+            (Spigot, ProjectType::Plugin, vec!["bukkit"], Some(Bukkit)),
+            (
+                Paper,
+                ProjectType::Plugin,
+                vec!["bukkit", "spigot"],
+                Some(Spigot),
+            ),
+            (
+                Fabric,
+                ProjectType::ResourcePack,
+                vec!["minecraft"],
+                Some(Minecraft),
+            ),
+            (
+                Fabric,
+                ProjectType::ShaderPack,
+                vec!["optifine", "iris"],
+                Some(Iris),
+            ),
+            (
+                Fabric,
+                ProjectType::DataPack,
+                vec!["datapack"],
+                Some(Datapack),
+            ),
+        ];
+        for (loader, project_type, built, expected) in cases {
+            let desc = descriptor(loader.clone(), Vec::new());
+            assert_eq!(
+                desc.resolved_loader(&built_for(&built), project_type),
+                expected,
+                "{project_type} for {loader} built for {built:?}"
+            );
+        }
     }
 }
@@ -0,0 +1,139 @@
+use reqwest::{blocking::Client, Url};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    config::Configuration,
+    mc_info::{Loader, MCVersion},
+    mrapi::client::parse_json,
+    util::error::{ApiError, MapitoError},
+};
+
+use super::{
+    constants::{
+        FABRIC_META_URL, FORGE_META_URL, FORGE_PROMOTIONS, LOADER_VERSIONS, NEOFORGE_META_URL,
+        NEOFORGE_VERSIONS, QUILT_META_URL,
+    },
+    defines::{ForgePromotions, LoaderEntry, MavenVersions},
+};
+
+/// Client for the apis listing the versions of the mod loaders
+pub struct MetaClient {
+    client: Client,
+    meta_url: Option<String>,
+}
+
+impl MetaClient {
+    pub fn new(config: &Configuration) -> Result<MetaClient, MapitoError> {
+        let meta_url = match config.loader_meta_url.as_deref().map(Url::parse) {
+            Some(Err(e)) => {
+                return Err(MapitoError::Config(format!(
+                    "invalid url '{}': {e}",
+                    config.loader_meta_url.as_deref().unwrap_or_default()
+                )))
+            }
+            Some(Ok(_)) => config
+                .loader_meta_url
+                .as_deref()
+                .map(|url| url.trim_end_matches('/').to_string()),
+            None => None,
+        };
+        Ok(MetaClient {
+            client: Client::new(),
+            meta_url,
+        })
+    }
+
+    /// base url of the meta api for a loader, a configured meta url replaces the host of
+    /// every loader with `<meta_url>/<loader>`.
+    fn base_url(&self, loader: &Loader) -> Option<String> {
+        let default = match loader {
+            Loader::Fabric => FABRIC_META_URL,
+            Loader::Quilt => QUILT_META_URL,
+            Loader::Forge => FORGE_META_URL,
+            Loader::Neoforge => NEOFORGE_META_URL,
+            _ => return None,
+        };
+        Some(match &self.meta_url {
+            Some(meta_url) => format!("{meta_url}/{loader}"),
+            None => default.to_string(),
+        })
+    }
+
+    fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, MapitoError> {
+        let response = self.client.get(url).send().map_err(ApiError::from);
+        response
+            .and_then(|response| match response.status().is_success() {
+                true => parse_json(response),
+                false => Err(ApiError::from_response(response)),
+            })
+            .map_err(MapitoError::Meta)
+    }
+
+    /// Versions of `loader` that can be used with Minecraft `mc_ver`, newest first.
+    /// Stable versions are preferred when there are any.
+    /// `None` for loaders mapito does not know a meta api for.
+    pub fn loader_versions(
+        &self,
+        loader: &Loader,
+        mc_ver: &MCVersion,
+    ) -> Result<Option<Vec<String>>, MapitoError> {
+        let Some(base_url) = self.base_url(loader) else {
+            return Ok(None);
+        };
+        let versions = match loader {
+            Loader::Fabric | Loader::Quilt => {
+                let entries: Vec<LoaderEntry> =
+                    self.get(&format!("{base_url}{LOADER_VERSIONS}/{mc_ver}"))?;
+                let (stable, unstable): (Vec<LoaderEntry>, Vec<LoaderEntry>) = entries
+                    .into_iter()
+                    .partition(|entry| entry.loader.stable && !entry.loader.version.contains('-'));
+                match stable.is_empty() {
+                    true => unstable,
+                    false => stable,
+                }
+                .into_iter()
+                .map(|entry| entry.loader.version)
+                .collect()
+            }
+            Loader::Forge => {
+                let promotions: ForgePromotions =
+                    self.get(&format!("{base_url}{FORGE_PROMOTIONS}"))?;
+                let mut versions: Vec<String> = Vec::new();
+                for kind in ["recommended", "latest"] {
+                    if let Some(version) = promotions.promos.get(&format!("{mc_ver}-{kind}")) {
+                        if !versions.contains(version) {
+                            versions.push(version.clone());
+                        }
+                    }
+                }
+                versions
+            }
+            _ => {
+                let maven: MavenVersions = self.get(&format!("{base_url}{NEOFORGE_VERSIONS}"))?;
+                let prefix = neoforge_prefix(mc_ver);
+                let (stable, unstable): (Vec<String>, Vec<String>) = maven
+                    .versions
+                    .into_iter()
+                    .rev()
+                    .filter(|version| version.starts_with(&prefix))
+                    .partition(|version| !version.contains('-'));
+                match stable.is_empty() {
+                    true => unstable,
+                    false => stable,
+                }
+            }
+        };
+        Ok(Some(versions))
+    }
+}
+
+/// start of the neoforge versions for a Minecraft version,
+/// neoforge drops the leading '1.' of the Minecraft version, e.g. `21.5.` for 1.21.5.
+pub fn neoforge_prefix(mc_ver: &MCVersion) -> String {
+    let mut parts = mc_ver.as_str().split('.').skip(1);
+    format!(
+        "{}.{}.",
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("0")
+    )
+}
@@ -0,0 +1,8 @@
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net/v2";
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org/v3";
+pub const FORGE_META_URL: &str = "https://files.minecraftforge.net/net/minecraftforge/forge";
+pub const NEOFORGE_META_URL: &str =
+    "https://maven.neoforged.net/api/maven/versions/releases/net/neoforged";
+pub const LOADER_VERSIONS: &str = "/versions/loader";
+pub const FORGE_PROMOTIONS: &str = "/promotions_slim.json";
+pub const NEOFORGE_VERSIONS: &str = "/neoforge";
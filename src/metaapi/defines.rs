@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// one entry of the fabric and quilt loader version lists
+#[derive(Deserialize, Debug)]
+pub struct LoaderEntry {
+    pub loader: LoaderVersion,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoaderVersion {
+    pub version: String,
+    /// quilt does not mark stable versions
+    #[serde(default = "stable_default")]
+    pub stable: bool,
+}
+
+fn stable_default() -> bool {
+    true
+}
+
+/// latest and recommended forge versions, keyed by `<mc version>-latest`
+/// and `<mc version>-recommended`
+#[derive(Deserialize, Debug)]
+pub struct ForgePromotions {
+    pub promos: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MavenVersions {
+    pub versions: Vec<String>,
+}
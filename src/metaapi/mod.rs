@@ -0,0 +1,3 @@
+pub mod client;
+pub mod constants;
+pub mod defines;
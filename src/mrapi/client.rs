@@ -1,56 +1,151 @@
-use std::borrow::Borrow;
+use std::{
+    borrow::Borrow,
+    cell::{Cell, OnceCell},
+    collections::HashMap,
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 use colored::Colorize;
 use reqwest::{
-    blocking::{Client, Response},
+    blocking::{Client, RequestBuilder, Response},
     Url,
 };
+use serde::de::DeserializeOwned;
 
 use crate::{
-    client::Downloader,
-    mc_info::{MCVersionUtils, MVDescriptor},
+    client::{write_verified, Downloader},
+    config::Configuration,
+    mc_info::{Loader, MCVersion, MVDescriptor, ProjectType, VT},
     mrapi::{constants::MEMBERS, defines::Member},
-    util::error::ApiError,
+    util::error::{ApiError, MapitoError},
 };
 
 use super::{
-    constants::{API_URL, FACETS, LIMIT, OFFSET, PROJECT, QUERY, SEARCH, VERSION},
-    defines::{Project, SearchResp, Version},
+    constants::{
+        BACKOFF_BASE_MS, BACKOFF_MAX_MS, CDN_URL, FACETS, FEATURED, GAME_VERSIONS, IDS, LIMIT,
+        LOADERS, OFFSET, PROJECT, PROJECTS, QUERY, RATELIMIT_MARGIN, RATELIMIT_REMAINING,
+        RATELIMIT_RESET, SEARCH, TAG_GAME_VERSION, VERSION, VERSIONS, VERSION_FILES_UPDATE,
+    },
+    defines::{FilesUpdateReq, GameVersionTag, Project, SearchResp, Version},
 };
 
+/// The rate limit state reported by the last response.
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    remaining: u32,
+    reset_at: Instant,
+}
+
 #[derive(Debug)]
 pub struct ApiClient {
     client: Client,
-    staging: usize,
+    api_url: String,
+    cdn_url: Option<String>,
+    max_retries: u32,
+    rate_limit: Cell<Option<RateLimit>>,
+    game_versions: OnceCell<Vec<GameVersionTag>>,
 }
 
 impl ApiClient {
-    /// create a new api client, that can send requests to either modrinths normal endpoint
-    /// or to the staging server depending on what bit is set.
-    pub fn new(is_staging: bool) -> ApiClient {
-        let staging = if is_staging { 1 } else { 0 };
-        ApiClient {
+    /// create a new api client, that sends requests to the api and downloads files from the
+    /// CDN given in the configuration.
+    /// Failed requests are retried up to `max_retries` times.
+    pub fn new(config: &Configuration) -> Result<ApiClient, MapitoError> {
+        let parse = |url: &str| match Url::parse(url) {
+            Ok(_) => Ok(url.trim_end_matches('/').to_string()),
+            Err(e) => Err(MapitoError::Config(format!("invalid url '{url}': {e}"))),
+        };
+        Ok(ApiClient {
             client: Client::new(),
-            staging,
+            api_url: parse(&config.api_url)?,
+            cdn_url: config.cdn_url.as_deref().map(parse).transpose()?,
+            max_retries: config.max_retries,
+            rate_limit: Cell::new(None),
+            game_versions: OnceCell::new(),
+        })
+    }
+
+    /// send a request, waiting for the rate limit to reset when it is about to run out and
+    /// retrying rate limited, server side and network errors with exponential backoff.
+    fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let mut attempt = 0;
+        loop {
+            self.throttle();
+            let attempt_request = request
+                .try_clone()
+                .expect("requests without streaming bodies can be cloned");
+            let error = match attempt_request.send() {
+                Ok(response) => {
+                    self.record_rate_limit(&response);
+                    if response.status().is_success() {
+                        return Ok(response);
+                    }
+                    ApiError::from_response(response)
+                }
+                Err(e) => e.into(),
+            };
+            if attempt >= self.max_retries || !error.is_retryable() {
+                return Err(error);
+            }
+            let delay = error.rate_limit_reset().unwrap_or(backoff(attempt));
+            println!(
+                "Request failed: {error}, retrying in {:.1}s ({}/{})",
+                delay.as_secs_f32(),
+                attempt + 1,
+                self.max_retries
+            );
+            sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// remember the rate limit headers of a response
+    fn record_rate_limit(&self, response: &Response) {
+        let header = |name: &str| -> Option<u64> {
+            response.headers().get(name)?.to_str().ok()?.parse().ok()
+        };
+        if let (Some(remaining), Some(reset)) =
+            (header(RATELIMIT_REMAINING), header(RATELIMIT_RESET))
+        {
+            self.rate_limit.set(Some(RateLimit {
+                remaining: remaining as u32,
+                reset_at: Instant::now() + Duration::from_secs(reset),
+            }));
+        }
+    }
+
+    /// wait for the rate limit window to reset if we are about to run out of requests
+    fn throttle(&self) {
+        if let Some(limit) = self.rate_limit.get() {
+            let now = Instant::now();
+            if limit.remaining <= RATELIMIT_MARGIN && limit.reset_at > now {
+                let wait = limit.reset_at - now;
+                println!(
+                    "Approaching rate limit, waiting {:.1}s for it to reset.",
+                    wait.as_secs_f32()
+                );
+                sleep(wait);
+                self.rate_limit.set(None);
+            }
         }
     }
 
     /// send a single request to modrinths api, with the given endpoint
-    fn request_api_par<I, K, V>(&self, endpoint: &String, params: I) -> Result<Response, ApiError>
+    fn request_api_par<I, K, V>(&self, endpoint: &str, params: I) -> Result<Response, ApiError>
     where
         I: IntoIterator,
         I::Item: Borrow<(K, V)>,
         K: AsRef<str>,
         V: AsRef<str>,
     {
-        let query =
-            Url::parse_with_params(&(API_URL[self.staging].to_owned() + endpoint), params).unwrap();
-        Ok(self.client.get(query).send()?)
+        let query = Url::parse_with_params(&(self.api_url.clone() + endpoint), params).unwrap();
+        self.send(self.client.get(query))
     }
 
-    fn request_api(&self, endpoint: &String) -> Result<Response, ApiError> {
-        let query = Url::parse(&(API_URL[self.staging].to_owned() + endpoint)).unwrap();
-        Ok(self.client.get(query).send()?)
+    fn request_api(&self, endpoint: &str) -> Result<Response, ApiError> {
+        let query = Url::parse(&(self.api_url.clone() + endpoint)).unwrap();
+        self.send(self.client.get(query))
     }
 
     pub fn search(
@@ -92,7 +187,7 @@ impl ApiClient {
                 }
                 str_facet += "]";
                 self.request_api_par(
-                    &SEARCH.to_string(),
+                    SEARCH,
                     &[
                         (QUERY, query),
                         (LIMIT, &par_limit),
@@ -102,17 +197,19 @@ impl ApiClient {
                 )
             }
             None => self.request_api_par(
-                &SEARCH.to_string(),
+                SEARCH,
                 &[(QUERY, query), (LIMIT, &par_limit), (OFFSET, &par_offset)],
             ),
         }?;
-        let query_response: SearchResp = query.json()?;
+        let query_response: SearchResp = parse_json(query)?;
 
         let mut slugs: Vec<String> = Vec::new();
-        let mut counter = 0;
-        for hit in query_response.hits {
-            let versions = hit["versions"].as_array().unwrap();
-            let latest = versions[versions.len() - 1].clone();
+        for (counter, hit) in query_response.hits.into_iter().enumerate() {
+            let latest = hit["versions"]
+                .as_array()
+                .and_then(|versions| versions.last())
+                .cloned()
+                .unwrap_or_default();
             println!(
                 "{counter} {}|{},{}, MC-{}, by: {}, downloads: {}\n{}\n",
                 hit["slug"].to_string().replace("\"", "").green(),
@@ -126,30 +223,23 @@ impl ApiClient {
                     .replace("\"", "")
                     .bright_black(),
             );
-            counter += 1;
             slugs.push(hit["slug"].to_string().replace("\"", ""));
         }
 
         Ok(slugs)
     }
 
-    pub fn get_project(&self, project_slug: &String) -> Result<Project, String> {
-        let project: Project = self
-            .request_api(&(PROJECT.to_string() + "/" + project_slug))
-            .expect("request_api")
-            .json()
-            .expect("json");
+    pub fn get_project(&self, project_slug: &str) -> Result<Project, ApiError> {
+        let project: Project =
+            parse_json(self.request_api(&(PROJECT.to_string() + "/" + project_slug))?)?;
 
         Ok(project)
     }
 
-    pub fn print_project_info(&self, project_slug: &String) {
-        let project = self.get_project(project_slug).expect("get_project");
-        let members: Vec<Member> = self
-            .request_api(&(PROJECT.to_string() + "/" + project_slug + MEMBERS))
-            .expect("request_api")
-            .json()
-            .expect("json");
+    pub fn print_project_info(&self, project_slug: &str) -> Result<(), ApiError> {
+        let project = self.get_project(project_slug)?;
+        let members: Vec<Member> =
+            parse_json(self.request_api(&(PROJECT.to_string() + "/" + project_slug + MEMBERS))?)?;
         println!("{}", project);
         println!(
             "members:\n{}",
@@ -157,39 +247,187 @@ impl ApiClient {
                 .iter()
                 .map(|m| { m.to_string() + "\n" })
                 .collect::<String>()
-        )
+        );
+        Ok(())
     }
 
-    pub fn get_project_version(
+    /// get multiple projects by their ids or slugs with a single request
+    pub fn get_projects(&self, ids: &[String]) -> Result<Vec<Project>, ApiError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = serde_json::to_string(ids).expect("string lists always serialize");
+        parse_json(self.request_api_par(PROJECTS, &[(IDS, ids)])?)
+    }
+
+    /// get multiple versions by their ids with a single request
+    pub fn get_versions(&self, ids: &[String]) -> Result<Vec<Version>, ApiError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = serde_json::to_string(ids).expect("string lists always serialize");
+        parse_json(self.request_api_par(VERSIONS, &[(IDS, ids)])?)
+    }
+
+    /// get the newest version matching `version_desc` and built for one of `loaders` for each
+    /// of the given sha512 file hashes.
+    /// Hashes modrinth does not know, or that have no matching version, are missing from the
+    /// returned map.
+    pub fn update_files(
+        &self,
+        hashes: Vec<String>,
+        version_desc: &MVDescriptor,
+        loaders: &[Loader],
+    ) -> Result<HashMap<String, Version>, ApiError> {
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let body = FilesUpdateReq {
+            hashes,
+            algorithm: "sha512".to_string(),
+            loaders: loaders.iter().map(Loader::to_string).collect(),
+            game_versions: self.game_version_filter(version_desc)?,
+            version_types: version_desc
+                .version_types
+                .iter()
+                .map(VT::to_string)
+                .collect(),
+        };
+        let url = Url::parse(&(self.api_url.clone() + VERSION_FILES_UPDATE)).unwrap();
+        parse_json(self.send(self.client.post(url).json(&body))?)
+    }
+
+    /// get all Minecraft versions known to modrinth, newest first.
+    /// The list is only requested once per client.
+    pub fn get_game_versions(&self) -> Result<&[GameVersionTag], ApiError> {
+        if let Some(versions) = self.game_versions.get() {
+            return Ok(versions);
+        }
+        let versions = parse_json(self.request_api(TAG_GAME_VERSION)?)?;
+        Ok(self.game_versions.get_or_init(|| versions))
+    }
+
+    /// the game versions a project version may list to fit `version_desc`,
+    /// an empty list means any game version is accepted.
+    pub fn game_version_filter(
         &self,
-        project_slug: &String,
         version_desc: &MVDescriptor,
-    ) -> Result<Version, ApiError> {
-        let mut project_version: Option<Version> = None;
-        let versions: Vec<Version> = self
-            .request_api(&(PROJECT.to_owned() + "/" + &project_slug + VERSION))?
-            .json()?;
-        if version_desc.mc_ver.is_latest() {
-            project_version = Some(versions[0].clone());
+    ) -> Result<Vec<MCVersion>, ApiError> {
+        Ok(match &version_desc.mc_range {
+            Some(range) => self
+                .get_game_versions()?
+                .iter()
+                .filter(|tag| range.matches(&tag.version))
+                .map(|tag| tag.version.clone())
+                .collect(),
+            None if version_desc.mc_ver.is_latest() => Vec::new(),
+            None => vec![version_desc.mc_ver.clone()],
+        })
+    }
+
+    /// turn "latest" into the newest release and "latest-snapshot" into the newest version of
+    /// any type, concrete versions are checked against the versions modrinth knows.
+    pub fn resolve_mc_version(&self, mc_ver: &MCVersion) -> Result<MCVersion, MapitoError> {
+        let versions = self.get_game_versions()?;
+        let resolved = if mc_ver.is_latest_snapshot() {
+            versions.first()
+        } else if mc_ver.is_latest() {
+            versions.iter().find(|v| v.version_type == "release")
         } else {
-            for version in versions {
-                if version_desc.check_version_compat(&version) {
-                    project_version = Some(version.clone());
-                    break;
-                }
-            }
+            versions.iter().find(|v| &v.version == mc_ver)
+        };
+        match resolved {
+            Some(tag) => Ok(tag.version.clone()),
+            None => Err(MapitoError::UnknownMCVersion(mc_ver.clone())),
         }
+    }
 
-        if project_version.is_none() {
-            return Err(ApiError::not_found());
+    /// get the newest version of a project of the given type that is compatible with
+    /// `version_desc`, builds for the native loader are preferred over compatible ones.
+    /// Loader and Minecraft version are filtered by modrinth, if `featured` is given only
+    /// versions with a matching featured flag are considered.
+    pub fn get_project_version(
+        &self,
+        project_slug: &str,
+        version_desc: &MVDescriptor,
+        project_type: ProjectType,
+        featured: Option<bool>,
+    ) -> Result<Version, MapitoError> {
+        let loaders = project_type.loaders(version_desc);
+        let mut params = vec![(
+            LOADERS,
+            serde_json::to_string(&loaders).expect("string lists always serialize"),
+        )];
+        let game_versions = self.game_version_filter(version_desc)?;
+        if !game_versions.is_empty() {
+            params.push((
+                GAME_VERSIONS,
+                serde_json::to_string(&game_versions).expect("string lists always serialize"),
+            ));
         }
+        if let Some(featured) = featured {
+            params.push((FEATURED, featured.to_string()));
+        }
+        let versions: Vec<Version> = parse_json(self.request_api_par(
+            &(PROJECT.to_owned() + "/" + project_slug + VERSION),
+            &params,
+        )?)?;
 
-        Ok(project_version.expect("Unknown Error"))
+        // modrinth lists the newest version first
+        loaders
+            .iter()
+            .find_map(|loader| {
+                versions.iter().find(|version| {
+                    version.loaders.contains(loader)
+                        && version_desc.check_version_compat(version, project_type)
+                })
+            })
+            .cloned()
+            .ok_or_else(|| MapitoError::IncompatibleVersion {
+                project: project_slug.to_string(),
+                mc_ver: version_desc.mc_ver.clone(),
+            })
     }
 }
 
+/// deserialize a json response body, remembering which endpoint it came from on failure
+pub(crate) fn parse_json<T: DeserializeOwned>(response: Response) -> Result<T, ApiError> {
+    let endpoint = response.url().path().to_string();
+    let body = response.text()?;
+    serde_json::from_str(&body).map_err(|e| ApiError::deserialize(&endpoint, e))
+}
+
+/// delay before retry number `attempt + 1`, doubling from [`BACKOFF_BASE_MS`] up to
+/// [`BACKOFF_MAX_MS`]
+fn backoff(attempt: u32) -> Duration {
+    let delay = 1u64.checked_shl(attempt).map_or(BACKOFF_MAX_MS, |factor| {
+        BACKOFF_BASE_MS.saturating_mul(factor)
+    });
+    Duration::from_millis(delay.min(BACKOFF_MAX_MS))
+}
+
 impl Downloader for ApiClient {
-    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), ApiError> {
-        self.client.download_file(path, url, hash)
+    fn download_file(&self, path: &str, url: &str, hash: &str) -> Result<(), MapitoError> {
+        let url = match (&self.cdn_url, url.strip_prefix(CDN_URL)) {
+            (Some(cdn_url), Some(file)) => cdn_url.clone() + file,
+            _ => url.to_string(),
+        };
+        let response = self.send(self.client.get(&url))?;
+        write_verified(path, &url, hash, &response.bytes()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(0), Duration::from_millis(BACKOFF_BASE_MS));
+        assert_eq!(backoff(1), Duration::from_millis(2 * BACKOFF_BASE_MS));
+        assert_eq!(backoff(20), Duration::from_millis(BACKOFF_MAX_MS));
+        assert_eq!(backoff(63), Duration::from_millis(BACKOFF_MAX_MS));
+        assert_eq!(backoff(64), Duration::from_millis(BACKOFF_MAX_MS));
+        assert_eq!(backoff(u32::MAX), Duration::from_millis(BACKOFF_MAX_MS));
     }
 }
@@ -1,14 +1,31 @@
-pub const API_URL: [&str; 2] = [
-    "https://api.modrinth.com/v2",
-    "https://staging-api.modrinth.com/v2",
-];
+pub const API_URL: &str = "https://api.modrinth.com/v2";
+pub const STAGING_API_URL: &str = "https://staging-api.modrinth.com/v2";
+/// Host all modrinth file urls point to, replaced when a CDN mirror is configured.
+pub const CDN_URL: &str = "https://cdn.modrinth.com";
 //API ENDPOINTS
 pub const SEARCH: &str = "/search";
 pub const PROJECT: &str = "/project";
 pub const VERSION: &str = "/version";
 pub const MEMBERS: &str = "/members";
+pub const PROJECTS: &str = "/projects";
+pub const VERSIONS: &str = "/versions";
+pub const VERSION_FILES_UPDATE: &str = "/version_files/update";
+pub const TAG_GAME_VERSION: &str = "/tag/game_version";
 //API PARAMS
 pub const QUERY: &str = "query";
 pub const OFFSET: &str = "offset";
 pub const LIMIT: &str = "limit";
 pub const FACETS: &str = "facets";
+pub const IDS: &str = "ids";
+pub const LOADERS: &str = "loaders";
+pub const GAME_VERSIONS: &str = "game_versions";
+pub const FEATURED: &str = "featured";
+//RATE LIMITING
+pub const RATELIMIT_REMAINING: &str = "X-Ratelimit-Remaining";
+pub const RATELIMIT_RESET: &str = "X-Ratelimit-Reset";
+/// Number of requests left in the current window at which we start waiting for the reset.
+pub const RATELIMIT_MARGIN: u32 = 2;
+/// Delay before the first retry, doubled with every further attempt.
+pub const BACKOFF_BASE_MS: u64 = 500;
+/// Longest delay between two retries.
+pub const BACKOFF_MAX_MS: u64 = 60_000;
@@ -4,9 +4,12 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+use crate::mc_info::deserialize_api_loaders;
 use crate::mc_info::Loader;
 use crate::mc_info::MCVersion;
+use crate::mc_info::SideSupport;
 use crate::mc_info::VT;
+use crate::util::error::MapitoError;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResp {
@@ -20,7 +23,10 @@ pub struct SearchResp {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Version {
+    pub id: String,
+    pub project_id: String,
     pub game_versions: Vec<MCVersion>,
+    #[serde(deserialize_with = "deserialize_api_loaders")]
     pub loaders: Vec<Loader>,
     pub name: String,
     pub version_number: String,
@@ -30,12 +36,41 @@ pub struct Version {
     pub dependencies: Vec<Dependency>,
 }
 
+impl Version {
+    /// the file marked as primary, the first file when none is marked
+    pub fn primary_file(&self) -> Result<&ApiFile, MapitoError> {
+        self.files
+            .iter()
+            .find(|file| file.primary)
+            .or(self.files.first())
+            .ok_or_else(|| MapitoError::InvalidEntry {
+                entry: self.name.clone(),
+                reason: format!("has no files in version {}", self.id),
+            })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiFile {
     pub url: String,
     pub hashes: Map<String, Value>,
     pub filename: String,
     pub size: u64,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl ApiFile {
+    /// the sha512 modrinth lists for the file
+    pub fn sha512(&self) -> Result<&str, MapitoError> {
+        self.hashes
+            .get("sha512")
+            .and_then(Value::as_str)
+            .ok_or_else(|| MapitoError::InvalidEntry {
+                entry: self.filename.clone(),
+                reason: "has no sha512 hash on modrinth".to_string(),
+            })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -47,6 +82,7 @@ pub struct Dependency {
 /// A modrinth Project, this can be a mod, modpack, resourcepack or shader
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Project {
+    pub id: String,
     pub slug: String,
     pub project_type: String,
     pub team: String,
@@ -58,8 +94,13 @@ pub struct Project {
     pub downloads: u32,
     pub game_versions: Vec<MCVersion>,
     pub categories: Vec<String>,
+    #[serde(deserialize_with = "deserialize_api_loaders")]
     pub loaders: Vec<Loader>,
     pub source_url: Option<String>,
+    #[serde(default)]
+    pub client_side: SideSupport,
+    #[serde(default)]
+    pub server_side: SideSupport,
 }
 
 impl Display for Project {
@@ -69,7 +110,12 @@ impl Display for Project {
             "Project: {}, latest-{}, {}\n {}\n\n Released: {}\n Last Updated: {} \n \
         loaders: {}\n supported versions: \n{} license: {}\n source: {}\n",
             self.title,
-            self.game_versions.last().expect("last"),
+            self.game_versions
+                .iter()
+                .filter(|v| v.is_release())
+                .reduce(|newest, v| if v > newest { v } else { newest })
+                .map(MCVersion::as_str)
+                .unwrap_or("none"),
             self.project_type.green(),
             self.description,
             self.published.yellow(),
@@ -97,6 +143,26 @@ impl Display for Project {
     }
 }
 
+/// A Minecraft version as listed by the game version tag endpoint
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameVersionTag {
+    pub version: MCVersion,
+    pub version_type: String,
+    pub date: String,
+    pub major: bool,
+}
+
+/// Request body for looking up the latest versions of files by their hashes
+#[derive(Debug, Serialize)]
+pub struct FilesUpdateReq {
+    pub hashes: Vec<String>,
+    pub algorithm: String,
+    pub loaders: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub game_versions: Vec<MCVersion>,
+    pub version_types: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct License {
     pub id: String,
@@ -122,3 +188,63 @@ impl Display for Member {
 pub struct User {
     pub username: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn version(files: Value) -> Version {
+        serde_json::from_value(json!({
+            "id": "v1",
+            "project_id": "p1",
+            "game_versions": ["1.21.5"],
+            "loaders": ["fabric"],
+            "name": "Mod 1.0",
+            "version_number": "1.0",
+            "downloads": 0,
+            "version_type": "release",
+            "files": files,
+            "dependencies": [],
+        }))
+        .expect("valid version")
+    }
+
+    fn file(name: &str, primary: bool) -> Value {
+        json!({
+            "url": format!("https://cdn.modrinth.com/{name}"),
+            "hashes": {"sha512": "ab", "sha1": "cd"},
+            "filename": name,
+            "size": 1,
+            "primary": primary,
+        })
+    }
+
+    #[test]
+    fn the_primary_file_is_preferred() {
+        let files = json!([file("sources.jar", false), file("mod.jar", true)]);
+        assert_eq!(version(files).primary_file().unwrap().filename, "mod.jar");
+        let unmarked = json!([file("first.jar", false), file("second.jar", false)]);
+        assert_eq!(
+            version(unmarked).primary_file().unwrap().filename,
+            "first.jar"
+        );
+    }
+
+    #[test]
+    fn missing_files_and_hashes_are_errors() {
+        assert!(matches!(
+            version(json!([])).primary_file(),
+            Err(MapitoError::InvalidEntry { .. })
+        ));
+        let mut no_sha512 = file("mod.jar", true);
+        no_sha512["hashes"] = json!({"sha1": "cd"});
+        let version = version(json!([no_sha512]));
+        let file = version.primary_file().unwrap();
+        assert!(matches!(
+            file.sha512(),
+            Err(MapitoError::InvalidEntry { .. })
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::read,
+    io::{self, Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    config::Configuration,
+    mc_info::{Loader, MCVersion, Side},
+    metaapi::client::MetaClient,
+    mrapi::client::ApiClient,
+    pack::{overrides::collect_files, pack::Pack},
+    util::{error::MapitoError, fs::write_atomic},
+};
+
+const EULA_STUB: &str =
+    "# Set this to true once you agree to the Minecraft EULA (https://aka.ms/MinecraftEULA).\n\
+eula=false\n";
+
+/// Build a deployable server directory from a pack: everything that is used on the server is
+/// installed below `dir` with the server overrides of the pack, together with a
+/// `server.properties` and `eula.txt` stub and a README
+/// describing which loader and Minecraft version to run.
+/// Existing `server.properties` and `eula.txt` files are left untouched.
+pub fn export_server(
+    pack: &mut Pack,
+    dir: &Path,
+    client: &ApiClient,
+    config: &Configuration,
+    mc_ver: Option<&MCVersion>,
+) -> Result<(), MapitoError> {
+    let target = pack.target(mc_ver)?;
+    println!("Exporting {} as a server to '{}'", pack.name, dir.display());
+    pack.install_to(
+        dir,
+        client,
+        config,
+        Some(&target),
+        Some(Side::Server),
+        false,
+    )?;
+
+    let properties = dir.join("server.properties");
+    if !properties.exists() {
+        write_atomic(
+            &properties,
+            format!(
+                "# generated by mapito for {}\nmotd={}\n",
+                pack.name, pack.name
+            )
+            .as_bytes(),
+        )?;
+    }
+    let eula = dir.join("eula.txt");
+    if !eula.exists() {
+        write_atomic(&eula, EULA_STUB.as_bytes())?;
+    }
+    write_atomic(
+        &dir.join("README.md"),
+        server_readme(pack, &target)?.as_bytes(),
+    )?;
+    println!("Exported {} to '{}'", pack.name, dir.display());
+    Ok(())
+}
+
+/// README for a server export, listing what has to be installed to run it
+fn server_readme(pack: &Pack, target: &MCVersion) -> Result<String, MapitoError> {
+    let mut readme = format!(
+        "# {}\n\nServer files exported by mapito.\n\n- Minecraft version: {target}\n- Loader: {}{}\n",
+        pack.name,
+        pack.version_info.loader,
+        match pack.version_info.for_target(target).loader_version {
+            Some(version) => format!(" {version}"),
+            None => String::new(),
+        }
+    );
+    let fallbacks = &pack.version_info.mod_loaders()[1..];
+    if !fallbacks.is_empty() {
+        readme += &format!(
+            "- Also uses builds for: {}\n",
+            fallbacks
+                .iter()
+                .map(|loader| loader.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    readme += &format!(
+        "\nInstall the {} server for Minecraft {target} into this directory, \
+        accept the EULA in `eula.txt` and start the server.\n\n## Contents\n\n",
+        pack.version_info.loader
+    );
+    let entries = pack.entries()?;
+    for (key, lock) in pack.locks(target)? {
+        if entries
+            .iter()
+            .any(|(k, entry)| k == &key && !entry.used_on(Side::Server))
+        {
+            continue;
+        }
+        readme += &format!(
+            "- {} ({}/{})\n",
+            lock.name,
+            lock.project_type.install_dir(),
+            lock.file_name
+        );
+    }
+    Ok(readme)
+}
+
+/// `mmc-pack.json` of a Prism Launcher/MultiMC instance
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MmcPack {
+    components: Vec<MmcComponent>,
+    format_version: u32,
+}
+
+#[derive(Serialize)]
+struct MmcComponent {
+    uid: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    important: bool,
+}
+
+/// Prism component uid of a loader, none for loaders Prism can not launch
+fn prism_uid(loader: &Loader) -> Option<&'static str> {
+    match loader {
+        Loader::Fabric => Some("net.fabricmc.fabric-loader"),
+        Loader::Quilt => Some("org.quiltmc.quilt-loader"),
+        Loader::Forge => Some("net.minecraftforge"),
+        Loader::Neoforge => Some("net.neoforged"),
+        Loader::Liteloader => Some("com.mumfrey.liteloader"),
+        _ => None,
+    }
+}
+
+/// Build a Prism Launcher/MultiMC instance from a pack: the client side of the pack is
+/// installed to `dir/.minecraft` next to an `instance.cfg` and a `mmc-pack.json` with the
+/// Minecraft and loader components. Without a pinned loader version the newest one is
+/// looked up, so the instance can be imported as it is.
+pub fn export_prism(
+    pack: &mut Pack,
+    dir: &Path,
+    client: &ApiClient,
+    meta: &MetaClient,
+    config: &Configuration,
+    mc_ver: Option<&MCVersion>,
+) -> Result<(), MapitoError> {
+    let target = pack.target(mc_ver)?;
+    let loader = pack.version_info.loader.clone();
+    let uid = prism_uid(&loader);
+    let loader_version = match uid {
+        Some(_) => pack.loader_version(meta, &target)?,
+        None => None,
+    };
+    println!(
+        "Exporting {} as a launcher instance to '{}'",
+        pack.name,
+        dir.display()
+    );
+    pack.install_to(
+        &dir.join(".minecraft"),
+        client,
+        config,
+        Some(&target),
+        Some(Side::Client),
+        false,
+    )?;
+
+    let mut components = vec![MmcComponent {
+        uid: "net.minecraft",
+        version: Some(target.to_string()),
+        important: true,
+    }];
+    match uid {
+        Some(uid) => {
+            if loader_version.is_none() {
+                println!(
+                    "Select the {loader} version to use on the Version page of the instance, \
+                    or pin one with 'pack pin-loader --version'."
+                );
+            }
+            components.push(MmcComponent {
+                uid,
+                version: loader_version,
+                important: false,
+            });
+        }
+        None => println!("The launcher has no component for {loader}, install it yourself."),
+    }
+    let mmc_pack = MmcPack {
+        components,
+        format_version: 1,
+    };
+    write_atomic(
+        &dir.join("mmc-pack.json"),
+        serde_json::to_string_pretty(&mmc_pack)
+            .expect("mmc packs always serialize")
+            .as_bytes(),
+    )?;
+    write_atomic(
+        &dir.join("instance.cfg"),
+        format!("InstanceType=OneSix\nname={}\niconKey=default\n", pack.name).as_bytes(),
+    )?;
+    println!("Exported {} to '{}'", pack.name, dir.display());
+    Ok(())
+}
+
+/// Pack all files below an exported directory into `DIR.zip`, e.g. for importing an instance
+/// into the launcher.
+pub fn zip_export(dir: &Path) -> Result<(), MapitoError> {
+    let zip_path = zip_path(dir)?;
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    collect_files(dir, dir, &mut files)?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (rel_path, path) in files {
+        zip.start_file(rel_path, SimpleFileOptions::default())
+            .map_err(io::Error::from)?;
+        zip.write_all(&read(path)?)?;
+    }
+    let body = zip.finish().map_err(io::Error::from)?.into_inner();
+    write_atomic(&zip_path, &body)?;
+    println!("Packed '{}' into '{}'", dir.display(), zip_path.display());
+    Ok(())
+}
+
+/// path of the zip next to `dir`, `out/` and `out` both give `out.zip`
+fn zip_path(dir: &Path) -> io::Result<PathBuf> {
+    let dir = match dir.file_name() {
+        Some(_) => dir.to_path_buf(),
+        None => dir.canonicalize()?,
+    };
+    let Some(name) = dir.file_name() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can not name a zip after '{}'", dir.display()),
+        ));
+    };
+    let mut zip_name = OsString::from(name);
+    zip_name.push(".zip");
+    Ok(dir.with_file_name(zip_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zips_are_named_after_the_directory() {
+        for dir in ["out", "out/", "out//", "./out/"] {
+            assert_eq!(
+                zip_path(Path::new(dir)).unwrap().file_name().unwrap(),
+                "out.zip",
+                "{dir}"
+            );
+        }
+        assert_eq!(
+            zip_path(Path::new("exports/my.pack/")).unwrap(),
+            Path::new("exports/my.pack.zip")
+        );
+        let current = std::env::current_dir().unwrap();
+        let mut current_zip = current.file_name().unwrap().to_os_string();
+        current_zip.push(".zip");
+        assert_eq!(
+            zip_path(Path::new(".")).unwrap(),
+            current.with_file_name(current_zip)
+        );
+        assert!(zip_path(Path::new("/")).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs::{read, read_to_string},
+    io::ErrorKind,
+    path::Path,
+};
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::input::read_line_to_string,
+    client::sha512_hex,
+    config::{Configuration, Instance},
+    mc_info::{MCVersion, Side},
+    mrapi::client::ApiClient,
+    pack::pack::Pack,
+    util::{error::MapitoError, fs::write_atomic},
+};
+
+/// Remembers which pack mapito installed to an instance.
+const MARKER: &str = ".mapito-instance.toml";
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum InstanceAction {
+    /// List the instances of the config and the pack installed to each of them
+    List,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InstalledPack {
+    pack: String,
+    mc_ver: MCVersion,
+    /// sha512 of the pack file at the time it was installed
+    sha512: String,
+}
+
+fn instance<'a>(name: &str, config: &'a Configuration) -> Result<&'a Instance, MapitoError> {
+    config
+        .instances
+        .get(name)
+        .ok_or_else(|| MapitoError::Config(format!("no instance named '{name}' configured")))
+}
+
+/// Install a pack to the instance `name` of the config, its default pack when `pack_name` is
+/// not given. The Minecraft version of the instance picks the target of the pack unless
+/// `mc_ver` is given, packs for a loader the instance can not run are refused.
+pub fn install_instance(
+    name: &str,
+    pack_name: Option<&str>,
+    client: &ApiClient,
+    config: &Configuration,
+    mc_ver: Option<&MCVersion>,
+    side: Option<Side>,
+    force: bool,
+) -> Result<(), MapitoError> {
+    let instance = instance(name, config)?;
+    let mut pack = match pack_name.or(instance.pack.as_deref()) {
+        Some(pack_name) => Pack::open(pack_name, config)?,
+        None => {
+            println!("Please enter the name of the Pack you want to install to {name}");
+            Pack::open(&read_line_to_string(), config)?
+        }
+    };
+    if let Some(loader) = &instance.loader {
+        let pack_loader = &pack.version_info.loader;
+        if loader != pack_loader && !loader.compatible_loaders().contains(pack_loader) {
+            return Err(MapitoError::Config(format!(
+                "instance '{name}' runs {loader}, '{}' is a {pack_loader} pack",
+                pack.name
+            )));
+        }
+    }
+    let target = pack.target(mc_ver.or(instance.mc_ver.as_ref()))?;
+    let dir = Path::new(&instance.dir);
+    pack.install_to(dir, client, config, Some(&target), side, force)?;
+
+    let installed = InstalledPack {
+        pack: pack.name.clone(),
+        mc_ver: target,
+        sha512: sha512_hex(&read(Pack::file_path(&pack.name, config))?),
+    };
+    write_atomic(&dir.join(MARKER), toml::to_string(&installed)?.as_bytes())?;
+    println!("Installed {} to instance {name}", pack.name);
+    Ok(())
+}
+
+/// Print every instance of the config with what is installed to it.
+pub fn list_instances(config: &Configuration) -> Result<(), MapitoError> {
+    if config.instances.is_empty() {
+        println!("No instances configured, add them as [instances.<name>] to the config.");
+        return Ok(());
+    }
+    for (name, instance) in &config.instances {
+        let mut details = Vec::new();
+        if let Some(pack) = &instance.pack {
+            details.push(format!("default pack {pack}"));
+        }
+        if let Some(mc_ver) = &instance.mc_ver {
+            details.push(format!("Minecraft {mc_ver}"));
+        }
+        if let Some(loader) = &instance.loader {
+            details.push(loader.to_string());
+        }
+        let details = match details.is_empty() {
+            true => String::new(),
+            false => format!(" ({})", details.join(", ")),
+        };
+        println!(
+            "{name} - {}{details}: {}",
+            instance.dir,
+            instance_state(instance, config)?
+        );
+    }
+    Ok(())
+}
+
+/// describes the pack installed to an instance and whether it changed since
+fn instance_state(instance: &Instance, config: &Configuration) -> Result<String, MapitoError> {
+    let dir = Path::new(&instance.dir);
+    if !dir.is_dir() {
+        return Ok("not created yet".to_string());
+    }
+    let installed: InstalledPack = match read_to_string(dir.join(MARKER)) {
+        Ok(body) => toml::from_str(&body)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok("no pack installed".to_string()),
+        Err(e) => return Err(e.into()),
+    };
+    let state = match read(Pack::file_path(&installed.pack, config)) {
+        Ok(body) if sha512_hex(&body) == installed.sha512 => "up to date",
+        Ok(_) => "pack changed since, install it again",
+        Err(e) if e.kind() == ErrorKind::NotFound => "pack was removed",
+        Err(e) => return Err(e.into()),
+    };
+    Ok(format!(
+        "{} for Minecraft {} installed, {state}",
+        installed.pack, installed.mc_ver
+    ))
+}
@@ -0,0 +1,240 @@
+use std::{
+    env,
+    fs::{read_dir, read_to_string},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    config::Configuration,
+    mc_info::{Loader, MCVersion, Side},
+    metaapi::client::neoforge_prefix,
+    mrapi::client::ApiClient,
+    pack::pack::Pack,
+    util::{error::MapitoError, fs::write_atomic},
+};
+
+/// file of the official launcher listing its installations
+const LAUNCHER_PROFILES: &str = "launcher_profiles.json";
+/// directory below the launcher directory the game directories of packs are created in
+const PROFILES_DIR: &str = "profiles";
+
+/// directory of the official launcher, where the launcher puts it on each platform
+fn launcher_dir() -> Result<PathBuf, MapitoError> {
+    if cfg!(windows) {
+        return match env::var_os("APPDATA") {
+            Some(path) => Ok(PathBuf::from(path).join(".minecraft")),
+            None => Err(MapitoError::Config("APPDATA is not set".to_owned())),
+        };
+    }
+    let Some(home) = env::home_dir() else {
+        return Err(MapitoError::Config("Home Dir not Found".to_owned()));
+    };
+    Ok(match cfg!(target_os = "macos") {
+        true => home.join("Library/Application Support/minecraft"),
+        false => home.join(".minecraft"),
+    })
+}
+
+/// Install the client side of a pack into its own game directory and add or update an
+/// installation for it in the official launcher, named after the pack.
+pub fn install_launcher_profile(
+    pack: &mut Pack,
+    client: &ApiClient,
+    config: &Configuration,
+    mc_ver: Option<&MCVersion>,
+    force: bool,
+) -> Result<(), MapitoError> {
+    let target = pack.target(mc_ver)?;
+    let launcher_dir = launcher_dir()?;
+    let profile_id = format!("mapito-{}", pack.name.to_lowercase().replace(" ", "-"));
+    let game_dir = launcher_dir.join(PROFILES_DIR).join(&profile_id);
+    pack.install_to(
+        &game_dir,
+        client,
+        config,
+        Some(&target),
+        Some(Side::Client),
+        force,
+    )?;
+
+    let version_desc = pack.version_info.for_target(&target);
+    let version_id = match version_desc
+        .loader_version
+        .as_deref()
+        .and_then(|version| pinned_version_id(&version_desc.loader, version, &target))
+        .or_else(|| loader_version_id(&launcher_dir, &version_desc.loader, &target))
+    {
+        Some(version_id) => version_id,
+        None => {
+            println!(
+                "No {} installation for Minecraft {target} found in the launcher, \
+                install it and select it in the '{}' installation.",
+                pack.version_info.loader, pack.name
+            );
+            target.to_string()
+        }
+    };
+    if version_id != target.as_str() && !launcher_dir.join("versions").join(&version_id).is_dir() {
+        println!(
+            "Install {version_id} in the launcher before playing '{}'.",
+            pack.name
+        );
+    }
+
+    let profiles_path = launcher_dir.join(LAUNCHER_PROFILES);
+    let mut profiles: Value = match read_to_string(&profiles_path) {
+        Ok(body) => serde_json::from_str(&body).map_err(|e| {
+            MapitoError::Config(format!("invalid {}: {e}", profiles_path.display()))
+        })?,
+        Err(e) if e.kind() == ErrorKind::NotFound => json!({"profiles": {}, "version": 3}),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(root) = profiles.as_object_mut() else {
+        return Err(MapitoError::Config(format!(
+            "invalid {}: not a json object",
+            profiles_path.display()
+        )));
+    };
+    let profile = root
+        .entry("profiles")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| {
+            MapitoError::Config(format!(
+                "invalid {}: profiles is not a json object",
+                profiles_path.display()
+            ))
+        })?
+        .entry(profile_id)
+        .or_insert_with(|| json!({"type": "custom", "icon": "Furnace"}));
+    if let Some(profile) = profile.as_object_mut() {
+        profile.insert("name".to_string(), json!(pack.name));
+        profile.insert("lastVersionId".to_string(), json!(version_id));
+        profile.insert("gameDir".to_string(), json!(game_dir.to_string_lossy()));
+    }
+    write_atomic(
+        &profiles_path,
+        serde_json::to_string_pretty(&profiles)
+            .expect("json values always serialize")
+            .as_bytes(),
+    )?;
+    println!(
+        "Launcher installation '{}' plays {version_id} in '{}'",
+        pack.name,
+        game_dir.display()
+    );
+    Ok(())
+}
+
+/// Id the launcher uses for a pinned loader version
+fn pinned_version_id(loader: &Loader, version: &str, mc_ver: &MCVersion) -> Option<String> {
+    match loader {
+        Loader::Fabric | Loader::Quilt => Some(format!("{loader}-loader-{version}-{mc_ver}")),
+        Loader::Forge => Some(format!("{mc_ver}-forge-{version}")),
+        Loader::Neoforge => Some(format!("neoforge-{version}")),
+        _ => None,
+    }
+}
+
+/// Id of the newest version of `loader` for `mc_ver` that is installed in the launcher,
+/// the Minecraft version itself for loaders the launcher does not need.
+fn loader_version_id(launcher_dir: &Path, loader: &Loader, mc_ver: &MCVersion) -> Option<String> {
+    let matches: Box<dyn Fn(&str) -> bool> = match loader {
+        Loader::Fabric | Loader::Quilt => {
+            let prefix = format!("{loader}-loader-");
+            let suffix = format!("-{mc_ver}");
+            Box::new(move |id| id.starts_with(&prefix) && id.ends_with(&suffix))
+        }
+        Loader::Forge => {
+            let prefix = format!("{mc_ver}-forge-");
+            Box::new(move |id| id.starts_with(&prefix))
+        }
+        Loader::Neoforge => {
+            let prefix = format!("neoforge-{}", neoforge_prefix(mc_ver));
+            Box::new(move |id| id.starts_with(&prefix))
+        }
+        _ => return Some(mc_ver.to_string()),
+    };
+    read_dir(launcher_dir.join("versions"))
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|id| matches(id))
+        .max_by_key(|id| version_key(id))
+}
+
+/// numbers in a version id, so newer builds compare greater
+fn version_key(id: &str) -> Vec<u64> {
+    id.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{create_dir_all, remove_dir_all},
+        process,
+    };
+
+    use super::*;
+
+    fn ver(s: &str) -> MCVersion {
+        s.parse().expect("valid version")
+    }
+
+    #[test]
+    fn pinned_loaders_map_to_launcher_ids() {
+        let mc_ver = ver("1.21.5");
+        let cases = [
+            (Loader::Fabric, "0.16.14", "fabric-loader-0.16.14-1.21.5"),
+            (Loader::Quilt, "0.26.0", "quilt-loader-0.26.0-1.21.5"),
+            (Loader::Forge, "55.0.1", "1.21.5-forge-55.0.1"),
+            (Loader::Neoforge, "21.5.75", "neoforge-21.5.75"),
+        ];
+        for (loader, version, id) in cases {
+            assert_eq!(
+                pinned_version_id(&loader, version, &mc_ver).as_deref(),
+                Some(id)
+            );
+        }
+        assert_eq!(pinned_version_id(&Loader::Paper, "1", &mc_ver), None);
+    }
+
+    #[test]
+    fn newest_installed_loader_is_used() {
+        let launcher_dir = env::temp_dir().join(format!("mapito-launcher-{}", process::id()));
+        for id in [
+            "1.21.5",
+            "fabric-loader-0.16.9-1.21.5",
+            "fabric-loader-0.16.14-1.21.5",
+            "fabric-loader-0.17.0-1.21.4",
+            "1.21.5-forge-55.0.9",
+            "1.21.5-forge-55.0.10",
+            "1.21.4-forge-54.1.0",
+            "neoforge-21.5.75",
+            "neoforge-21.4.100",
+        ] {
+            create_dir_all(launcher_dir.join("versions").join(id)).unwrap();
+        }
+        let mc_ver = ver("1.21.5");
+        let id = |loader: Loader| loader_version_id(&launcher_dir, &loader, &mc_ver);
+        assert_eq!(
+            id(Loader::Fabric).as_deref(),
+            Some("fabric-loader-0.16.14-1.21.5")
+        );
+        assert_eq!(id(Loader::Quilt), None);
+        assert_eq!(id(Loader::Forge).as_deref(), Some("1.21.5-forge-55.0.10"));
+        assert_eq!(id(Loader::Neoforge).as_deref(), Some("neoforge-21.5.75"));
+        assert_eq!(id(Loader::Minecraft).as_deref(), Some("1.21.5"));
+        remove_dir_all(&launcher_dir).unwrap();
+    }
+
+    #[test]
+    fn version_keys_compare_numerically() {
+        assert!(version_key("1.21.5-forge-55.0.10") > version_key("1.21.5-forge-55.0.9"));
+        assert_eq!(version_key("neoforge-21.5.75-beta"), vec![21, 5, 75]);
+    }
+}
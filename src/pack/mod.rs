@@ -1,27 +1,310 @@
-use std::fs::{create_dir_all, File};
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap};
 
 use pack::Pack;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use toml::{self};
 
-use crate::mc_info::VT;
+use crate::ghapi::client::GithubClient;
+use crate::ghapi::defines::{Asset, Release};
+use crate::mc_info::{
+    deserialize_api_loader, Loader, MCVersion, ProjectType, Side, SideSupport, VT,
+};
+use crate::metaapi::client::MetaClient;
 use crate::mrapi::client::ApiClient;
-use crate::mrapi::defines::Dependency;
-use crate::util::error::ApiError;
+use crate::mrapi::defines::{Dependency, Version};
+use crate::util::error::MapitoError;
+use crate::util::fs::{write_atomic, FileLock};
 use crate::{config::Configuration, MVDescriptor};
 
+pub mod export;
+pub mod instance;
+pub mod launcher;
+mod overrides;
+#[allow(clippy::module_inception)]
 pub mod pack;
+pub mod packwiz;
+
+/// Where the file of a pack entry comes from
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum Source {
+    /// a project version on modrinth, the table key is the project slug
+    #[default]
+    Modrinth,
+    /// any download url, the sha512 of the file is required
+    Url,
+    /// a local file copied in at install
+    Path,
+    /// a release asset of a github repository, updated to the newest matching release
+    Github,
+}
+
+impl Source {
+    fn is_modrinth(&self) -> bool {
+        *self == Self::Modrinth
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug)]
 struct PackMod {
     name: String,
+    #[serde(default, skip_serializing_if = "Source::is_modrinth")]
+    source: Source,
+    /// the local file of a `path` entry, relative paths start at the directory of the pack file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// the `owner/name` repository of a `github` entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repo: Option<String>,
+    /// regex the release asset name of a `github` entry has to match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    asset: Option<String>,
+    #[serde(default)]
+    project_type: ProjectType,
+    /// the loader the locked file was chosen for
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_api_loader"
+    )]
+    loader: Option<Loader>,
+    #[serde(default)]
     verstion_type: VT,
+    #[serde(default)]
     version_number: String,
+    #[serde(default)]
     file_url: String,
+    #[serde(default)]
     file_name: String,
+    #[serde(default)]
     sha512: String,
+    project_id: Option<String>,
+    version_id: Option<String>,
+    #[serde(default)]
     dependencies: Vec<Dependency>,
+    #[serde(default, skip_serializing_if = "SideSupport::is_unknown")]
+    client_side: SideSupport,
+    #[serde(default, skip_serializing_if = "SideSupport::is_unknown")]
+    server_side: SideSupport,
+    /// locked files for the further target Minecraft versions of the pack
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    targets: BTreeMap<String, PackMod>,
+}
+
+impl PackMod {
+    /// create a pack entry for the primary file of a project version
+    fn from_version(
+        version: Version,
+        project_type: ProjectType,
+        version_desc: &MVDescriptor,
+    ) -> Result<Self, MapitoError> {
+        let file = version.primary_file()?;
+        let (file_url, sha512, file_name) = (
+            file.url.clone(),
+            file.sha512()?.to_string(),
+            file.filename.clone(),
+        );
+        Ok(PackMod {
+            loader: version_desc.resolved_loader(&version, project_type),
+            name: version.name,
+            source: Source::Modrinth,
+            path: None,
+            repo: None,
+            asset: None,
+            project_type,
+            verstion_type: version.version_type,
+            version_number: version.version_number,
+            file_url,
+            sha512,
+            file_name,
+            project_id: Some(version.project_id),
+            version_id: Some(version.id),
+            dependencies: version.dependencies,
+            client_side: SideSupport::Unknown,
+            server_side: SideSupport::Unknown,
+            targets: BTreeMap::new(),
+        })
+    }
+
+    /// create a pack entry for a file outside of modrinth
+    fn from_file(name: String, source: Source, location: String, sha512: String) -> Self {
+        let (file_url, path) = match source {
+            Source::Path => (String::new(), Some(location)),
+            _ => (location, None),
+        };
+        let mut entry = PackMod {
+            name,
+            source,
+            path,
+            repo: None,
+            asset: None,
+            project_type: ProjectType::Mod,
+            loader: None,
+            verstion_type: VT::Release,
+            version_number: String::new(),
+            file_url,
+            file_name: String::new(),
+            sha512,
+            project_id: None,
+            version_id: None,
+            dependencies: Vec::new(),
+            client_side: SideSupport::Unknown,
+            server_side: SideSupport::Unknown,
+            targets: BTreeMap::new(),
+        };
+        entry.file_name = entry.default_file_name();
+        entry
+    }
+
+    /// create a `github` entry for an asset of a release, `asset_pattern` picks the asset
+    /// in later releases.
+    fn from_release(
+        name: String,
+        repo: String,
+        asset_pattern: String,
+        release: Release,
+        asset: Asset,
+        sha512: String,
+    ) -> Self {
+        let mut entry =
+            PackMod::from_file(name, Source::Github, asset.browser_download_url, sha512);
+        entry.repo = Some(repo);
+        entry.asset = Some(asset_pattern);
+        entry.file_name = asset.name;
+        entry.version_number = release.tag_name;
+        if release.prerelease {
+            entry.verstion_type = VT::Beta;
+        }
+        entry
+    }
+
+    /// file name of an entry that does not set one, taken from the end of its url or path
+    fn default_file_name(&self) -> String {
+        let location = match self.source {
+            Source::Path => self.path.as_deref().unwrap_or_default(),
+            _ => self.file_url.as_str(),
+        };
+        let location = location.split(['?', '#']).next().unwrap_or_default();
+        location
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// check that an entry has everything needed to install it safely
+    fn validate(&self, key: &str) -> Result<(), MapitoError> {
+        check_key(key)?;
+        let reason = match self.source {
+            Source::Modrinth | Source::Url if self.file_url.is_empty() => "needs a file_url",
+            Source::Path if self.path.is_none() => "needs a path",
+            Source::Github if self.github_repo(key).is_err() => "needs a repo as owner/name",
+            Source::Github if self.asset.is_none() => "needs an asset pattern",
+            Source::Github if self.file_url.is_empty() => {
+                "has no release locked yet, update the pack first"
+            }
+            _ if self.sha512.is_empty() => "needs a sha512 to verify its file",
+            _ if self.sha512.len() != 128
+                || !self.sha512.bytes().all(|b| b.is_ascii_hexdigit()) =>
+            {
+                "has a sha512 that is not 128 hex digits"
+            }
+            _ if !is_plain_file_name(&self.file_name) => {
+                "needs a file_name without path separators"
+            }
+            _ => return Ok(()),
+        };
+        Err(MapitoError::InvalidEntry {
+            entry: key.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+
+    /// the `owner/name` repository of a `github` entry
+    fn github_repo(&self, key: &str) -> Result<&str, MapitoError> {
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part != "."
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        };
+        match self.repo.as_deref() {
+            Some(repo)
+                if repo
+                    .split_once('/')
+                    .is_some_and(|(o, n)| valid(o) && valid(n)) =>
+            {
+                Ok(repo)
+            }
+            Some(repo) => Err(MapitoError::InvalidEntry {
+                entry: key.to_string(),
+                reason: format!("has a repo '{repo}' that is not written as owner/name"),
+            }),
+            None => Err(MapitoError::InvalidEntry {
+                entry: key.to_string(),
+                reason: "needs a repo".to_string(),
+            }),
+        }
+    }
+
+    /// compile the asset pattern of a `github` entry
+    fn asset_pattern(&self, key: &str) -> Result<Regex, MapitoError> {
+        let invalid = |reason: String| MapitoError::InvalidEntry {
+            entry: key.to_string(),
+            reason,
+        };
+        let pattern = self
+            .asset
+            .as_deref()
+            .ok_or_else(|| invalid("needs an asset pattern".to_string()))?;
+        Regex::new(pattern).map_err(|e| invalid(format!("has an invalid asset pattern: {e}")))
+    }
+
+    /// take the locked file for a further target Minecraft version out of this entry
+    fn take_target(&mut self, target: &MCVersion) -> Option<PackMod> {
+        let key = self
+            .targets
+            .keys()
+            .find(|key| key.parse::<MCVersion>().is_ok_and(|key| &key == target))?
+            .clone();
+        self.targets.remove(&key)
+    }
+
+    /// lock a file for a further target Minecraft version, replacing the one locked before
+    /// under any spelling of the version
+    fn set_target(&mut self, target: &MCVersion, lock: PackMod) {
+        while self.take_target(target).is_some() {}
+        self.targets.insert(target.normalized(), lock);
+    }
+
+    /// whether this entry has to be installed on the given side,
+    /// entries without side information are installed everywhere.
+    fn used_on(&self, side: Side) -> bool {
+        let support = match side {
+            Side::Client => self.client_side,
+            Side::Server => self.server_side,
+        };
+        support != SideSupport::Unsupported
+    }
+}
+
+/// whether `name` names a file inside of the directory it is joined to
+pub(crate) fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':', '\0'])
+}
+
+/// entry keys name the metafiles of exports, so they follow the rules of file names
+fn check_key(key: &str) -> Result<(), MapitoError> {
+    match is_plain_file_name(key) {
+        true => Ok(()),
+        false => Err(MapitoError::InvalidEntry {
+            entry: key.to_string(),
+            reason: "needs a name without path separators".to_string(),
+        }),
+    }
 }
 
 impl PartialEq for PackMod {
@@ -34,75 +317,406 @@ pub fn create_pack(
     client: &ApiClient,
     name: String,
     version_desc: MVDescriptor,
-    mods: &Vec<String>,
+    mods: &[String],
     config: &Configuration,
-) {
+) -> Result<(), MapitoError> {
     let mut pack = Pack::new();
     pack.name = name;
     pack.version_info = version_desc.clone();
+    pack.version_info.mc_ver = client.resolve_mc_version(&version_desc.mc_ver)?;
+    pack.version_info.targets = version_desc
+        .targets
+        .iter()
+        .map(|target| client.resolve_mc_version(target))
+        .collect::<Result<_, _>>()?;
 
-    for mc_mod in mods {
-        if !pack
-            .add_mod(mc_mod, client)
-            .contains(&pack.version_info.mc_ver)
-        {
-            panic!("added incompatible mod version");
+    for (mc_mod, added) in pack.add_mods(mods, client)? {
+        let game_versions = added?;
+        if !pack.version_info.accepts_game_versions(&game_versions) {
+            return Err(MapitoError::IncompatibleVersion {
+                project: mc_mod.to_string(),
+                mc_ver: pack.version_info.mc_ver,
+            });
         }
     }
 
-    create_dir_all(config.pack_path.clone()).expect("create_dir_all");
-    let mut pack_fd = File::create(
-        config.pack_path.clone()
-            + "/"
-            + &pack.name.to_lowercase().as_str().replace(" ", "-")
-            + ".mtpck",
-    )
-    .expect("create");
-
-    write!(
-        &mut pack_fd,
-        "{}",
-        toml::to_string(&pack).expect("to_string")
-    )
-    .expect("write");
+    let path = Pack::file_path(&pack.name, config);
+    let _lock = FileLock::acquire(&path)?;
+    write_atomic(&path, toml::to_string(&pack)?.as_bytes())?;
     println!(
         "Created Pack: {}, Minecraft-{}",
         pack.name, pack.version_info.mc_ver
     );
+    Ok(())
 }
 
 pub fn update_pack(
     client: &ApiClient,
     name: String,
     config: &Configuration,
-) -> Result<(), ApiError> {
-    let mut pack = Pack::open(&name, config);
+) -> Result<(), MapitoError> {
+    let mut pack = Pack::open(&name, config)?;
+    if pack.version_info.mc_ver.is_latest() {
+        let resolved = client.resolve_mc_version(&pack.version_info.mc_ver)?;
+        println!(
+            "Pinning Minecraft version '{}' of {name} to {resolved}.",
+            pack.version_info.mc_ver
+        );
+        pack.version_info.mc_ver = resolved;
+    }
     println!("Updating mod entries in {name} Modpack.");
-    for (key, value) in pack.mods.clone() {
-        let mut mod_version: PackMod = value.try_into().expect("try_into");
-        let project_version = client.get_project_version(&key, &pack.version_info)?;
+    let targets: Vec<MCVersion> = pack.version_info.all_targets().cloned().collect();
+    let entries = pack.entries()?;
+    let ids: Vec<String> = entries
+        .iter()
+        .filter_map(|(_, m)| m.project_id.clone())
+        .collect();
+    let projects = client.get_projects(&ids)?;
+    let github = GithubClient::new(config)?;
+    for (key, mut entry) in entries {
+        if entry.source == Source::Github {
+            match update_release(&github, &key, &entry, &pack.version_info.version_types) {
+                Ok(Some(updated)) => entry = updated,
+                Ok(None) => {}
+                Err(e) => println!(
+                    "Could not update {key}, keeping {}: {e}",
+                    entry.version_number
+                ),
+            }
+        }
+        // drop locks of removed targets and spell the others like new locks
+        entry.targets = std::mem::take(&mut entry.targets)
+            .into_iter()
+            .filter_map(|(key, lock)| {
+                let target = key.parse::<MCVersion>().ok()?;
+                targets
+                    .contains(&target)
+                    .then(|| (target.normalized(), lock))
+            })
+            .collect();
+        if let Some(project) = projects
+            .iter()
+            .find(|p| entry.project_id.as_ref() == Some(&p.id))
+        {
+            entry.client_side = project.client_side;
+            entry.server_side = project.server_side;
+        }
+        pack.mods.insert(key, toml::Value::try_from(entry)?);
+    }
+    for target in &targets {
+        update_target(client, &mut pack, target)?;
+    }
+    let pack_name = pack.name.clone();
+    pack.save(config)?;
+    println!("To install the Updated mods, use '--pack install' for {pack_name}");
+    Ok(())
+}
+
+/// Pin the loader version of a pack to `version`, or to the newest version the meta api of the
+/// loader lists for the Minecraft version of the pack.
+pub fn pin_loader(
+    client: &ApiClient,
+    meta: &MetaClient,
+    pack: &mut Pack,
+    version: Option<String>,
+    config: &Configuration,
+) -> Result<(), MapitoError> {
+    let loader = pack.version_info.loader.clone();
+    let mc_ver = client.resolve_mc_version(&pack.version_info.mc_ver)?;
+    let versions = match meta.loader_versions(&loader, &mc_ver)? {
+        Some(versions) => versions,
+        None if version.is_some() => Vec::new(),
+        None => {
+            println!("mapito can not look up {loader} versions, pin one with --version.");
+            return Ok(());
+        }
+    };
+    if !versions.is_empty() {
+        println!(
+            "Newest {loader} versions for Minecraft {mc_ver}: {}",
+            versions
+                .iter()
+                .take(5)
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    let pinned = match version {
+        Some(version) => {
+            if !versions.is_empty() && !versions.contains(&version) {
+                println!("{loader} {version} is not one of the listed versions.");
+            }
+            version
+        }
+        None => versions
+            .into_iter()
+            .next()
+            .ok_or(MapitoError::IncompatibleVersion {
+                project: loader.to_string(),
+                mc_ver: mc_ver.clone(),
+            })?,
+    };
+    println!("Pinning {loader} {pinned} for {}.", pack.name);
+    pack.version_info.loader_version = Some(pinned);
+    pack.save(config)
+}
+
+/// Move the locked files for one target Minecraft version of a pack to their newest versions,
+/// mods that are not locked for the target yet get locked.
+fn update_target(
+    client: &ApiClient,
+    pack: &mut Pack,
+    target: &MCVersion,
+) -> Result<(), MapitoError> {
+    if !pack.version_info.targets.is_empty() {
+        println!("Minecraft {target}:");
+    }
+    let version_desc = pack.version_info.for_target(target);
+    let locks: Vec<(String, PackMod)> = pack
+        .locks(target)?
+        .into_iter()
+        .filter(|(_, m)| m.source.is_modrinth())
+        .collect();
+    let latest = newest_versions(client, &locks, &version_desc)?;
+    for (key, entry) in pack.entries()? {
+        match entry.source {
+            Source::Modrinth => {}
+            // already moved to its newest release by update_pack
+            Source::Github => continue,
+            Source::Url | Source::Path => {
+                println!("Skipping {key}, it does not come from modrinth.");
+                continue;
+            }
+        }
+        let Some((_, mod_version)) = locks.iter().find(|(k, _)| k == &key) else {
+            println!("Locking {key} for Minecraft {target}.");
+            let project_version =
+                match client.get_project_version(&key, &version_desc, entry.project_type, None) {
+                    Ok(version) => version,
+                    Err(MapitoError::IncompatibleVersion { .. }) => {
+                        println!("{key} has no build for Minecraft {target}, leaving it unlocked.");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+            pack.set_lock(
+                &key,
+                target,
+                PackMod::from_version(project_version, entry.project_type, &version_desc)?,
+            )?;
+            continue;
+        };
+        let project_version = match latest.get(&mod_version.sha512) {
+            Some(version) => version.clone(),
+            None => match client.get_project_version(
+                &key,
+                &version_desc,
+                mod_version.project_type,
+                None,
+            ) {
+                Ok(version) => version,
+                Err(MapitoError::IncompatibleVersion { .. }) => {
+                    println!(
+                        "{key} has no build for Minecraft {target} anymore, keeping {}.",
+                        mod_version.version_number
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e),
+            },
+        };
         if mod_version.version_number != project_version.version_number {
             println!(
                 "Found new version of {}\nOld: {}\nNew: {}",
                 mod_version.name, mod_version.version_number, project_version.version_number
             );
-            pack.mods.remove::<String>(&key.clone());
-            mod_version.name = project_version.name;
-            mod_version.verstion_type = project_version.version_type;
-            mod_version.version_number = project_version.version_number;
-            mod_version.file_url = project_version.files[0].url.clone();
-            mod_version.sha512 = project_version.files[0].hashes["sha512"]
-                .to_string()
-                .replace("\"", "");
-            mod_version.file_name = project_version.files[0].filename.clone();
-            pack.mods
-                .insert(key, toml::Value::try_from(&mod_version).expect("try_from"));
+            pack.set_lock(
+                &key,
+                target,
+                PackMod::from_version(project_version, mod_version.project_type, &version_desc)?,
+            )?;
         } else {
             println!("Mod {} is up to Date.", mod_version.name)
         }
     }
-    let pack_name = pack.name.clone();
-    pack.save(config);
-    println!("To install the Updated mods, use '--pack install' for {pack_name}");
     Ok(())
 }
+
+/// Move a `github` entry to the asset of the newest release of its repository that matches
+/// its asset pattern. The asset is downloaded to compute its sha512.
+/// Returns `None` when the entry stays as it is.
+fn update_release(
+    github: &GithubClient,
+    key: &str,
+    entry: &PackMod,
+    version_types: &[VT],
+) -> Result<Option<PackMod>, MapitoError> {
+    let repo = entry.github_repo(key)?.to_string();
+    let pattern = entry.asset_pattern(key)?;
+    let Some((release, asset)) = github.newest_asset(&repo, &pattern, version_types)? else {
+        println!("No release of {repo} has an asset matching '{pattern}', keeping {key}.");
+        return Ok(None);
+    };
+    if entry.file_url == asset.browser_download_url && !entry.sha512.is_empty() {
+        println!("Mod {} is up to Date.", entry.name);
+        return Ok(None);
+    }
+    println!(
+        "Found new release of {}\nOld: {}\nNew: {}",
+        entry.name, entry.version_number, release.tag_name
+    );
+    let sha512 = github.hash_asset(&asset)?;
+    let mut updated = PackMod::from_release(
+        entry.name.clone(),
+        repo,
+        pattern.to_string(),
+        release,
+        asset,
+        sha512,
+    );
+    updated.project_type = entry.project_type;
+    updated.client_side = entry.client_side;
+    updated.server_side = entry.server_side;
+    Ok(Some(updated))
+}
+
+/// newest versions for the files of the given locks, keyed by their sha512 hash.
+/// Files are looked up in one request per project type and accepted loader, so builds for a
+/// preferred loader win over builds for a compatible one.
+fn newest_versions(
+    client: &ApiClient,
+    locks: &[(String, PackMod)],
+    version_desc: &MVDescriptor,
+) -> Result<HashMap<String, Version>, MapitoError> {
+    let mut project_types: Vec<ProjectType> = Vec::new();
+    for (_, lock) in locks {
+        if !project_types.contains(&lock.project_type) {
+            project_types.push(lock.project_type);
+        }
+    }
+    let mut latest = HashMap::new();
+    for project_type in project_types {
+        for loader in project_type.loaders(version_desc) {
+            let hashes: Vec<String> = locks
+                .iter()
+                .filter(|(_, m)| m.project_type == project_type)
+                .filter(|(_, m)| !latest.contains_key(&m.sha512))
+                .map(|(_, m)| m.sha512.clone())
+                .collect();
+            latest.extend(client.update_files(hashes, version_desc, &[loader])?);
+        }
+    }
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA512: &str = "ee5f4b3823019c88561e03fa820517c282c62cc00b634ea2e87c60c4b54f734597e44f435931f6a35ceb8f794b3f03614bc31707338f2c75c506474545cd02d9";
+
+    fn url_entry(url: &str, sha512: &str) -> PackMod {
+        PackMod::from_file(
+            "entry".to_string(),
+            Source::Url,
+            url.to_string(),
+            sha512.to_string(),
+        )
+    }
+
+    #[test]
+    fn entries_need_a_valid_sha512() {
+        assert!(url_entry("https://example.com/a.jar", SHA512)
+            .validate("a")
+            .is_ok());
+        assert!(url_entry("https://example.com/a.jar", "")
+            .validate("a")
+            .is_err());
+        assert!(url_entry("https://example.com/a.jar", &SHA512[1..])
+            .validate("a")
+            .is_err());
+        assert!(
+            url_entry("https://example.com/a.jar", &SHA512.replace('e', "x"))
+                .validate("a")
+                .is_err()
+        );
+
+        let mut modrinth = url_entry("https://cdn.modrinth.com/a.jar", "");
+        modrinth.source = Source::Modrinth;
+        assert!(modrinth.validate("a").is_err());
+        modrinth.sha512 = SHA512.to_string();
+        assert!(modrinth.validate("a").is_ok());
+    }
+
+    #[test]
+    fn file_names_stay_in_their_directory() {
+        let mut entry = url_entry("https://example.com/a.jar", SHA512);
+        assert_eq!(entry.file_name, "a.jar");
+        for name in [
+            "",
+            ".",
+            "..",
+            "../a.jar",
+            "mods/a.jar",
+            "..\\a.jar",
+            "C:a.jar",
+        ] {
+            entry.file_name = name.to_string();
+            assert!(entry.validate("a").is_err(), "'{name}' should be rejected");
+        }
+        entry.file_name = "a..b.jar".to_string();
+        assert!(entry.validate("a").is_ok());
+    }
+
+    #[test]
+    fn only_unsupported_sides_are_left_out() {
+        use SideSupport::*;
+        for (support, used) in [
+            (Required, true),
+            (Optional, true),
+            (Unsupported, false),
+            (Unknown, true),
+        ] {
+            let mut entry = url_entry("https://example.com/a.jar", SHA512);
+            entry.client_side = support;
+            assert_eq!(entry.used_on(Side::Client), used, "client {support:?}");
+            assert!(
+                entry.used_on(Side::Server),
+                "server with client {support:?}"
+            );
+            entry.client_side = Unknown;
+            entry.server_side = support;
+            assert_eq!(entry.used_on(Side::Server), used, "server {support:?}");
+            assert!(
+                entry.used_on(Side::Client),
+                "client with server {support:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn keys_name_plain_files() {
+        let entry = url_entry("https://example.com/a.jar", SHA512);
+        assert!(entry.validate("my-mod").is_ok());
+        for key in ["../../x", "a/b", "a\\b", ".."] {
+            assert!(entry.validate(key).is_err(), "{key}");
+        }
+    }
+
+    #[test]
+    fn github_repos_are_owner_and_name() {
+        let mut entry = url_entry("", "");
+        entry.source = Source::Github;
+        for repo in ["CaffeineMC/sodium", "a-b/c_d.e"] {
+            entry.repo = Some(repo.to_string());
+            assert_eq!(entry.github_repo("a").ok(), Some(repo));
+        }
+        for repo in ["sodium", "a/b/c", "/b", "a/", "../b", "a/..", "a/b?x=1"] {
+            entry.repo = Some(repo.to_string());
+            assert!(entry.github_repo("a").is_err(), "{repo}");
+        }
+        entry.repo = None;
+        assert!(entry.github_repo("a").is_err());
+    }
+}
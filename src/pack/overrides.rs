@@ -0,0 +1,219 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read, read_dir, read_to_string},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::sha512_hex,
+    mc_info::Side,
+    util::{error::MapitoError, fs::write_atomic},
+};
+
+/// Files copied to every installation.
+pub const OVERRIDES: &str = "overrides";
+/// Files only copied to client installations.
+pub const CLIENT_OVERRIDES: &str = "client-overrides";
+/// Files only copied to server installations.
+pub const SERVER_OVERRIDES: &str = "server-overrides";
+/// Remembers the hashes of the override files mapito wrote to an instance.
+const MANIFEST: &str = ".mapito-overrides.toml";
+
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    files: BTreeMap<String, String>,
+}
+
+/// The override directories that apply to an installation for `side`, in the order they are
+/// copied. Installations without a side are treated as client installations.
+fn override_dirs(pack_dir: &Path, side: Option<Side>) -> Vec<PathBuf> {
+    let side_dir = match side {
+        Some(Side::Server) => SERVER_OVERRIDES,
+        Some(Side::Client) | None => CLIENT_OVERRIDES,
+    };
+    vec![pack_dir.join(OVERRIDES), pack_dir.join(side_dir)]
+}
+
+/// Copy the override files of a pack into the instance at `root`.
+/// Files that were changed in the instance since mapito last wrote them are only replaced
+/// with `force`. Without a `side` the client overrides are copied, as for a client
+/// installation.
+pub fn apply_overrides(
+    pack_dir: &Path,
+    root: &Path,
+    side: Option<Side>,
+    force: bool,
+) -> Result<(), MapitoError> {
+    let mut files = BTreeMap::new();
+    for dir in override_dirs(pack_dir, side) {
+        collect_files(&dir, &dir, &mut files)?;
+    }
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let manifest_path = root.join(MANIFEST);
+    let mut manifest: Manifest = match read_to_string(&manifest_path) {
+        Ok(body) => toml::from_str(&body)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Manifest::default(),
+        Err(e) => return Err(e.into()),
+    };
+    for (rel_path, source) in files {
+        let body = read(&source)?;
+        let hash = sha512_hex(&body);
+        let target = root.join(&rel_path);
+        let current = match read(&target) {
+            Ok(current) => Some(sha512_hex(&current)),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if current.as_ref() == Some(&hash) {
+            manifest.files.insert(rel_path, hash);
+            continue;
+        }
+        let changed_locally =
+            current.is_some() && current != manifest.files.get(&rel_path).cloned();
+        if changed_locally && !force {
+            println!("Keeping locally changed '{rel_path}', use --force to overwrite it.");
+            continue;
+        }
+        println!("Copying override '{rel_path}'");
+        write_atomic(&target, &body)?;
+        manifest.files.insert(rel_path, hash);
+    }
+    write_atomic(&manifest_path, toml::to_string(&manifest)?.as_bytes())?;
+    Ok(())
+}
+
+/// All files below `dir` keyed by their path relative to `base`, later directories win.
+pub fn collect_files(
+    base: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> Result<(), MapitoError> {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(base, &path, files)?;
+        } else if let Ok(rel_path) = path.strip_prefix(base) {
+            // always use '/' so manifests stay portable
+            let rel_path = rel_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(rel_path, path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{create_dir_all, remove_dir_all, write},
+        process,
+    };
+
+    use super::*;
+
+    /// a pack directory with one file per override directory, and an empty instance
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("mapito-overrides-{name}-{}", process::id()));
+        let _ = remove_dir_all(&dir);
+        let pack_dir = dir.join("pack");
+        for (overrides, body) in [
+            (OVERRIDES, "common"),
+            (CLIENT_OVERRIDES, "client"),
+            (SERVER_OVERRIDES, "server"),
+        ] {
+            create_dir_all(pack_dir.join(overrides).join("config")).unwrap();
+            write(pack_dir.join(overrides).join("config/common.cfg"), "common").unwrap();
+            write(pack_dir.join(overrides).join("config/side.cfg"), body).unwrap();
+        }
+        let root = dir.join("instance");
+        create_dir_all(&root).unwrap();
+        (pack_dir, root)
+    }
+
+    fn side_cfg(root: &Path) -> String {
+        read_to_string(root.join("config/side.cfg")).unwrap()
+    }
+
+    #[test]
+    fn side_overrides_replace_common_ones() {
+        for (side, expected) in [
+            (Some(Side::Server), "server"),
+            (Some(Side::Client), "client"),
+            (None, "client"),
+        ] {
+            let (pack_dir, root) = setup(&format!("{side:?}"));
+            apply_overrides(&pack_dir, &root, side, false).unwrap();
+            assert_eq!(side_cfg(&root), expected);
+            assert_eq!(
+                read_to_string(root.join("config/common.cfg")).unwrap(),
+                "common"
+            );
+            remove_dir_all(pack_dir.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn files_written_before_are_updated() {
+        let (pack_dir, root) = setup("update");
+        apply_overrides(&pack_dir, &root, Some(Side::Client), false).unwrap();
+        write(
+            pack_dir.join(CLIENT_OVERRIDES).join("config/side.cfg"),
+            "new",
+        )
+        .unwrap();
+        apply_overrides(&pack_dir, &root, Some(Side::Client), false).unwrap();
+        assert_eq!(side_cfg(&root), "new");
+        let manifest: Manifest =
+            toml::from_str(&read_to_string(root.join(MANIFEST)).unwrap()).unwrap();
+        assert_eq!(manifest.files["config/side.cfg"], sha512_hex(b"new"));
+        remove_dir_all(pack_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn local_changes_are_kept_without_force() {
+        let (pack_dir, root) = setup("local");
+        apply_overrides(&pack_dir, &root, Some(Side::Client), false).unwrap();
+        write(root.join("config/side.cfg"), "tuned").unwrap();
+        write(
+            pack_dir.join(CLIENT_OVERRIDES).join("config/side.cfg"),
+            "new",
+        )
+        .unwrap();
+
+        apply_overrides(&pack_dir, &root, Some(Side::Client), false).unwrap();
+        assert_eq!(side_cfg(&root), "tuned");
+        apply_overrides(&pack_dir, &root, Some(Side::Client), true).unwrap();
+        assert_eq!(side_cfg(&root), "new");
+        remove_dir_all(pack_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn existing_files_are_only_kept_when_they_differ() {
+        let (pack_dir, root) = setup("existing");
+        create_dir_all(root.join("config")).unwrap();
+        write(root.join("config/common.cfg"), "common").unwrap();
+        write(root.join("config/side.cfg"), "mine").unwrap();
+        apply_overrides(&pack_dir, &root, Some(Side::Client), false).unwrap();
+        assert_eq!(side_cfg(&root), "mine");
+        let manifest: Manifest =
+            toml::from_str(&read_to_string(root.join(MANIFEST)).unwrap()).unwrap();
+        assert!(manifest.files.contains_key("config/common.cfg"));
+        assert!(!manifest.files.contains_key("config/side.cfg"));
+        remove_dir_all(pack_dir.parent().unwrap()).unwrap();
+    }
+}
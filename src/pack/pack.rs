@@ -1,21 +1,32 @@
-use core::panic;
 use std::{
+    collections::HashMap,
     fmt::Display,
-    fs::{create_dir_all, read_dir, remove_file, File},
-    io::{Read, Write},
+    fs::{read, read_dir, rename, File},
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 use toml::Table;
 
 use crate::{
     cli::input::confirm_input,
-    client::Downloader,
+    client::{sha512_hex, write_verified, Downloader},
     config::Configuration,
-    mc_info::{Loader, MCVersion, MVDescriptor, VT},
-    mrapi::client::ApiClient,
-    pack::PackMod,
+    ghapi::client::GithubClient,
+    mc_info::{Loader, MCVersion, MVDescriptor, ProjectType, Side, VT},
+    metaapi::client::MetaClient,
+    mrapi::{
+        client::ApiClient,
+        defines::{Project, Version},
+    },
+    pack::{check_key, newest_versions, overrides::apply_overrides, PackMod, Source},
+    util::{
+        error::{ApiError, MapitoError},
+        fs::{write_atomic, FileLock},
+    },
 };
 
 #[derive(Debug, Clone, Subcommand)]
@@ -27,7 +38,58 @@ pub enum PackAction {
     /// Modify an existing pack
     Modify,
     /// Install an existing pack
-    Install,
+    Install {
+        /// Name of the pack, asked for when not given and there is no default for the instance
+        pack: Option<String>,
+        /// Which of the target Minecraft versions of the pack to install, defaults to the main one
+        #[arg(long, value_name = "VERSION")]
+        mc: Option<MCVersion>,
+        /// Only install what is used on this side of the game
+        #[arg(long, value_enum)]
+        side: Option<Side>,
+        /// Overwrite override files that were changed in the instance
+        #[arg(long)]
+        force: bool,
+        /// Install into a game directory of its own and add an installation for it to the
+        /// official launcher
+        #[arg(long, conflicts_with = "side")]
+        launcher_profile: bool,
+        /// Install into an instance of the config instead of the install path
+        #[arg(long, value_name = "NAME", conflicts_with = "launcher_profile")]
+        instance: Option<String>,
+    },
+    /// Export an existing pack
+    Export {
+        /// Write a ready to run server directory to DIR
+        #[arg(long, value_name = "DIR", conflicts_with = "format")]
+        server: Option<PathBuf>,
+        /// Format to export the pack in
+        #[arg(long, value_enum, requires = "dir")]
+        format: Option<ExportFormat>,
+        /// Directory to export the pack to
+        #[arg(required_unless_present = "server", requires = "format")]
+        dir: Option<PathBuf>,
+        /// Also pack the exported directory into DIR.zip, e.g. to import an instance into the launcher
+        #[arg(long, requires = "format")]
+        zip: bool,
+        /// Which of the target Minecraft versions of the pack to export, defaults to the main one
+        #[arg(long, value_name = "VERSION")]
+        mc: Option<MCVersion>,
+    },
+    /// Pin the loader version of a pack, looked up from the meta api of the loader
+    PinLoader {
+        /// Loader version to pin instead of the newest one
+        #[arg(long, value_name = "VERSION")]
+        version: Option<String>,
+    },
+    /// Create a pack from a pack in another format
+    Import {
+        /// Format of the pack to import
+        #[arg(long, value_enum)]
+        format: PackFormat,
+        /// Directory of the pack to import
+        dir: PathBuf,
+    },
     /// Remove an existing pack
     Remove,
     /// List all packs
@@ -40,7 +102,10 @@ impl Display for PackAction {
             PackAction::Create => "create",
             PackAction::Update => "update",
             PackAction::Modify => "modify",
-            PackAction::Install => "install",
+            PackAction::Install { .. } => "install",
+            PackAction::Export { .. } => "export",
+            PackAction::PinLoader { .. } => "pin the loader of",
+            PackAction::Import { .. } => "import",
             PackAction::Remove => "remove",
             PackAction::List => "list",
         };
@@ -48,11 +113,34 @@ impl Display for PackAction {
     }
 }
 
+/// Pack formats of other tools mapito can convert from
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PackFormat {
+    /// packwiz `pack.toml`, `index.toml` and `.pw.toml` metafiles
+    Packwiz,
+}
+
+/// Formats mapito can export packs to
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// packwiz `pack.toml`, `index.toml` and `.pw.toml` metafiles
+    Packwiz,
+    /// a Prism Launcher/MultiMC instance
+    Prism,
+}
+
+/// Minecraft versions of the file for the main target of a mod added to a pack, or why it
+/// could not be added
+pub type AddResult = Result<Vec<MCVersion>, MapitoError>;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pack {
     pub name: String,
     pub version_info: MVDescriptor,
     pub mods: Table,
+    /// lock on the pack file, held from [`Pack::open`] until the pack is dropped
+    #[serde(skip)]
+    lock: Option<Rc<FileLock>>,
 }
 
 impl Pack {
@@ -61,149 +149,571 @@ impl Pack {
         Pack {
             name: "".to_string(),
             version_info: MVDescriptor {
-                mc_ver: MCVersion::new(),
+                mc_ver: MCVersion::latest(),
+                mc_range: None,
+                targets: Vec::new(),
                 version_types: vec![VT::Release, VT::Beta, VT::Alpha],
                 loader: Loader::Fabric,
+                loader_version: None,
+                accepted_loaders: Vec::new(),
             },
             mods: Table::new(),
+            lock: None,
         }
     }
 
-    /// open the pack file for the given modpack and return Pack object
-    pub fn open(name: &str, config: &Configuration) -> Self {
-        let mut pack_file = File::open(
-            config.pack_path.clone()
-                + "/"
-                + name.to_lowercase().as_str().replace(" ", "-").as_str()
-                + ".mtpck",
-        )
-        .expect("open");
+    /// path of the pack file for a pack with the given name
+    pub fn file_path(name: &str, config: &Configuration) -> PathBuf {
+        PathBuf::from(&config.pack_path).join(name.to_lowercase().replace(" ", "-") + ".mtpck")
+    }
+
+    /// path of the directory next to the pack file holding the override files of the pack
+    pub fn dir_path(name: &str, config: &Configuration) -> PathBuf {
+        PathBuf::from(&config.pack_path).join(name.to_lowercase().replace(" ", "-"))
+    }
+
+    /// open the pack file for the given modpack and return Pack object.
+    /// Other mapito processes can not open the pack until it is dropped, so changes
+    /// saved in between are not lost.
+    pub fn open(name: &str, config: &Configuration) -> Result<Self, MapitoError> {
+        let path = Pack::file_path(name, config);
+        if !path.exists() {
+            return Err(MapitoError::PackNotFound(name.to_string()));
+        }
+        let lock = FileLock::acquire(&path)?;
+        let mut pack = Pack::read(&path, name)?;
+        pack.lock = Some(lock);
+        Ok(pack)
+    }
+
+    /// read the pack file at `path` without locking it, for looking at a pack only
+    fn read(path: &Path, name: &str) -> Result<Self, MapitoError> {
+        let mut pack_file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(MapitoError::PackNotFound(name.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
         let mut body = String::new();
 
-        pack_file.read_to_string(&mut body).expect("read_to_string");
+        pack_file.read_to_string(&mut body)?;
 
-        toml::from_str::<Pack>(&body).expect("from_string")
+        Ok(toml::from_str::<Pack>(&body)?)
     }
 
     /// Print all mods contained in the Pack
     pub fn list_mods(&self) {
         println!("The Pack contains the following mods:");
         for (key, info) in &self.mods {
-            println!("{key} - {}", info["name"]);
+            match info.get("loader").and_then(|loader| loader.as_str()) {
+                Some(loader) => println!("{key} - {} ({loader})", info["name"]),
+                None => println!("{key} - {}", info["name"]),
+            }
         }
     }
 
     /// write this pack to File, at the path given in the config
-    pub fn save(&self, config: &Configuration) {
+    pub fn save(&self, config: &Configuration) -> Result<(), MapitoError> {
         println!("Saving Changes for {}", self.name);
-        create_dir_all(config.pack_path.clone()).expect("create_dir_all");
-        let mut pack_fd = File::create(
-            config.pack_path.clone()
-                + "/"
-                + &self.name.to_lowercase().as_str().replace(" ", "-")
-                + ".mtpck",
-        )
-        .expect("create");
+        let path = Pack::file_path(&self.name, config);
+        let _lock = FileLock::acquire(&path)?;
+        write_atomic(&path, toml::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
 
-        write!(
-            &mut pack_fd,
-            "{}",
-            toml::to_string(self).expect("to_string")
-        )
-        .expect("write");
+    /// remove pack from file system, its override files are kept
+    pub fn remove(&self, config: &Configuration) -> Result<(), MapitoError> {
+        FileLock::acquire(&Pack::file_path(&self.name, config))?.remove()?;
+        Ok(())
     }
 
-    /// remove pack from file system
-    pub fn remove(&self, config: &Configuration) {
-        remove_file(
-            config.pack_path.clone()
-                + "/"
-                + &self.name.to_lowercase().as_str().replace(" ", "-")
-                + ".mtpck",
-        )
-        .expect("remove_file");
+    /// rename the pack, moving its pack file and override files.
+    /// The pack is saved under the new name before anything is removed, a failed move of the
+    /// override files leaves the pack under its old name.
+    pub fn rename(&mut self, name: String, config: &Configuration) -> Result<(), MapitoError> {
+        let old_path = Pack::file_path(&self.name, config);
+        let new_path = Pack::file_path(&name, config);
+        if new_path == old_path {
+            self.name = name;
+            return self.save(config);
+        }
+        let lock = FileLock::acquire(&new_path)?;
+        if new_path.exists() {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("a pack named '{name}' already exists"),
+            )
+            .into());
+        }
+        let old_name = std::mem::replace(&mut self.name, name);
+        if let Err(e) = self.save(config) {
+            self.name = old_name;
+            return Err(e);
+        }
+        let old_dir = Pack::dir_path(&old_name, config);
+        if old_dir.is_dir() {
+            let new_dir = Pack::dir_path(&self.name, config);
+            if let Err(e) = rename(&old_dir, &new_dir) {
+                self.name = old_name;
+                lock.remove()?;
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!(
+                        "could not move '{}' to '{}': {e}",
+                        old_dir.display(),
+                        new_dir.display()
+                    ),
+                )
+                .into());
+            }
+        }
+        FileLock::acquire(&old_path)?.remove()?;
+        self.lock = Some(lock);
+        Ok(())
     }
 
-    /// adds a mod and its dependencies
-    pub fn add_mod(&mut self, mod_slug: &String, client: &ApiClient) -> Vec<MCVersion> {
-        println!("Looking for {mod_slug}");
+    /// adds mods and their dependencies, locked for every target Minecraft version of the pack.
+    /// The projects are looked up with a single request.
+    pub fn add_mods(
+        &mut self,
+        mod_slugs: &[String],
+        client: &ApiClient,
+    ) -> Result<Vec<(String, AddResult)>, MapitoError> {
+        let projects = client.get_projects(mod_slugs)?;
+        Ok(mod_slugs
+            .iter()
+            .map(|mod_slug| {
+                println!("Looking for {mod_slug}");
+                let added = match projects
+                    .iter()
+                    .find(|p| &p.slug == mod_slug || &p.id == mod_slug)
+                {
+                    Some(project) => self.add_project(project, client),
+                    None => Err(ApiError::not_found().into()),
+                };
+                (mod_slug.clone(), added)
+            })
+            .collect())
+    }
 
-        let (mod_version, game_versions) = self.fetch_mod(mod_slug, client);
+    /// adds a project and its dependencies, see [`Pack::add_mods`]
+    fn add_project(
+        &mut self,
+        project: &Project,
+        client: &ApiClient,
+    ) -> Result<Vec<MCVersion>, MapitoError> {
+        let mod_slug = &project.slug;
+        let project_type = ProjectType::of_project(project, &self.version_info.loader);
+        let (mut mod_version, game_versions) =
+            self.fetch_mod(mod_slug, &self.version_info.mc_ver, project_type, client)?;
+        mod_version.client_side = project.client_side;
+        mod_version.server_side = project.server_side;
+        for target in &self.version_info.targets {
+            match self.fetch_mod(mod_slug, target, project_type, client) {
+                Ok((lock, _)) => mod_version.set_target(target, lock),
+                Err(MapitoError::IncompatibleVersion { .. }) => println!(
+                    "'{mod_slug}' has no build for Minecraft {target}, leaving it unlocked."
+                ),
+                Err(e) => return Err(e),
+            }
+        }
 
-        self.mods.insert(
-            mod_slug.to_string(),
-            toml::Value::try_from(&mod_version).expect("try_from"),
-        );
+        self.mods
+            .insert(mod_slug.to_string(), toml::Value::try_from(&mod_version)?);
         println!(
-            "Found mod '{}' and added it to pack",
+            "Found {} '{}' and added it to pack",
+            mod_version.project_type,
             mod_version.name.replace("\"", "")
         );
-        for dependency in mod_version.dependencies {
-            let dep_slug = client
-                .get_project(&dependency.project_id)
-                .expect("get_project_info")
-                .slug;
-            if dependency.dependency_type == "required" && !self.mods.contains_key(&dep_slug) {
+        let mut dep_ids: Vec<String> = Vec::new();
+        for dependency in std::iter::once(&mod_version)
+            .chain(mod_version.targets.values())
+            .flat_map(|lock| &lock.dependencies)
+            .filter(|dependency| dependency.dependency_type == "required")
+        {
+            if !dep_ids.contains(&dependency.project_id) {
+                dep_ids.push(dependency.project_id.clone());
+            }
+        }
+        for dependency in client.get_projects(&dep_ids)? {
+            if !self.mods.contains_key(&dependency.slug) {
                 println!("Dependency: ");
 
-                self.add_mod(&dep_slug, client);
+                self.add_project(&dependency, client)?;
             }
         }
-        game_versions
+        Ok(game_versions)
+    }
+
+    /// all mod entries of the pack with their table keys
+    pub(super) fn entries(&self) -> Result<Vec<(String, PackMod)>, MapitoError> {
+        self.mods
+            .iter()
+            .map(|(key, value)| {
+                let mut entry: PackMod = value.clone().try_into()?;
+                if entry.file_name.is_empty() {
+                    entry.file_name = entry.default_file_name();
+                }
+                Ok((key.clone(), entry))
+            })
+            .collect()
+    }
+
+    /// adds a file from any url, it is checked against `sha512` on install
+    pub fn add_url(&mut self, key: &str, url: String, sha512: String) -> Result<(), MapitoError> {
+        check_key(key)?;
+        let entry = PackMod::from_file(key.to_string(), Source::Url, url, sha512);
+        self.mods
+            .insert(key.to_string(), toml::Value::try_from(&entry)?);
+        println!("Added '{}' to pack", entry.file_name);
+        Ok(())
+    }
+
+    /// adds a local file that is copied in on install,
+    /// it has to keep the content it has now.
+    pub fn add_local(
+        &mut self,
+        key: &str,
+        path: String,
+        config: &Configuration,
+    ) -> Result<(), MapitoError> {
+        check_key(key)?;
+        let sha512 = sha512_hex(&read(PathBuf::from(&config.pack_path).join(&path))?);
+        let entry = PackMod::from_file(key.to_string(), Source::Path, path, sha512);
+        self.mods
+            .insert(key.to_string(), toml::Value::try_from(&entry)?);
+        println!("Added '{}' to pack", entry.file_name);
+        Ok(())
+    }
+
+    /// adds the newest release asset of a github repository that matches `asset_pattern`,
+    /// later updates move it to newer matching releases.
+    pub fn add_github(
+        &mut self,
+        key: &str,
+        repo: String,
+        asset_pattern: String,
+        github: &GithubClient,
+    ) -> Result<(), MapitoError> {
+        check_key(key)?;
+        let mut entry = PackMod::from_file(
+            key.to_string(),
+            Source::Github,
+            String::new(),
+            String::new(),
+        );
+        entry.repo = Some(repo.clone());
+        entry.asset = Some(asset_pattern);
+        entry.github_repo(key)?;
+        let pattern = entry.asset_pattern(key)?;
+        let Some((release, asset)) =
+            github.newest_asset(&repo, &pattern, &self.version_info.version_types)?
+        else {
+            return Err(MapitoError::InvalidEntry {
+                entry: key.to_string(),
+                reason: format!("has no release of {repo} with an asset matching '{pattern}'"),
+            });
+        };
+        let sha512 = github.hash_asset(&asset)?;
+        let entry = PackMod::from_release(
+            key.to_string(),
+            repo,
+            pattern.to_string(),
+            release,
+            asset,
+            sha512,
+        );
+        self.mods
+            .insert(key.to_string(), toml::Value::try_from(&entry)?);
+        println!(
+            "Added '{}' {} to pack",
+            entry.file_name, entry.version_number
+        );
+        Ok(())
+    }
+
+    /// the locked files of all mods for one target Minecraft version,
+    /// mods that are not locked for the target yet are left out.
+    /// Files outside of modrinth are used for every target.
+    pub(super) fn locks(&self, target: &MCVersion) -> Result<Vec<(String, PackMod)>, MapitoError> {
+        let main = target == &self.version_info.mc_ver;
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter_map(
+                |(key, mut entry)| match main || !entry.source.is_modrinth() {
+                    true => Some((key, entry)),
+                    false => entry.take_target(target).map(|lock| (key, lock)),
+                },
+            )
+            .collect())
     }
 
-    /// Downloads all mods from the pack to the download path given in the configuration.
+    /// replace the locked file of a mod for one target Minecraft version
+    pub(super) fn set_lock(
+        &mut self,
+        key: &str,
+        target: &MCVersion,
+        lock: PackMod,
+    ) -> Result<(), MapitoError> {
+        let entry: Option<PackMod> = self
+            .mods
+            .get(key)
+            .cloned()
+            .map(|v| v.try_into())
+            .transpose()?;
+        let entry = match entry {
+            Some(mut entry) if target != &self.version_info.mc_ver => {
+                entry.set_target(target, lock);
+                entry
+            }
+            Some(entry) => PackMod {
+                client_side: entry.client_side,
+                server_side: entry.server_side,
+                targets: entry.targets,
+                ..lock
+            },
+            None => lock,
+        };
+        self.mods
+            .insert(key.to_string(), toml::Value::try_from(&entry)?);
+        Ok(())
+    }
+
+    /// Check all entries against modrinth with as few requests as possible.
+    /// Entries whose file was replaced are updated to the new file of the same version, entries
+    /// whose version was removed from modrinth are moved to the newest compatible version.
+    fn verify_entries(
+        &mut self,
+        client: &ApiClient,
+        config: &Configuration,
+        target: &MCVersion,
+    ) -> Result<(), MapitoError> {
+        println!("Verifying pack entries.");
+        let entries = self.locks(target)?;
+        let ids: Vec<String> = entries
+            .iter()
+            .filter_map(|(_, m)| m.version_id.clone())
+            .collect();
+        let versions: HashMap<String, Version> = client
+            .get_versions(&ids)?
+            .into_iter()
+            .map(|v| (v.id.clone(), v))
+            .collect();
+
+        let mut changed = false;
+        let mut removed = Vec::new();
+        for (key, mod_version) in entries {
+            let Some(id) = &mod_version.version_id else {
+                continue;
+            };
+            match versions.get(id) {
+                Some(version) => {
+                    let current = PackMod::from_version(
+                        version.clone(),
+                        mod_version.project_type,
+                        &self.version_info.for_target(target),
+                    )?;
+                    if current.sha512 != mod_version.sha512 {
+                        println!("The file of '{key}' changed on modrinth, updating entry.");
+                        self.set_lock(&key, target, current)?;
+                        changed = true;
+                    }
+                }
+                None => removed.push((key, mod_version)),
+            }
+        }
+
+        let replacements =
+            newest_versions(client, &removed, &self.version_info.for_target(target))?;
+        for (key, mod_version) in removed {
+            match replacements.get(&mod_version.sha512) {
+                Some(version) => {
+                    println!(
+                        "'{key}' {} is no longer available, using {} instead.",
+                        mod_version.version_number, version.version_number
+                    );
+                    self.set_lock(
+                        &key,
+                        target,
+                        PackMod::from_version(
+                            version.clone(),
+                            mod_version.project_type,
+                            &self.version_info.for_target(target),
+                        )?,
+                    )?;
+                    changed = true;
+                }
+                None => println!(
+                    "'{key}' {} is no longer available.",
+                    mod_version.version_number
+                ),
+            }
+        }
+
+        if changed {
+            self.save(config)?;
+        }
+        Ok(())
+    }
+
+    /// Downloads all files of the pack into the directory for their project type below the
+    /// install path given in the configuration.
+    /// `mc_ver` picks one of the target Minecraft versions of the pack, the main one by default.
+    /// With a `side` given, entries that are not used on that side are skipped.
+    /// Afterwards the override files of the pack are copied, see [`apply_overrides`].
     /// If a download fails once we try and update the mod entry in the pack and redo the download
     /// once.
-    pub fn install(&mut self, client: &ApiClient, config: &Configuration) {
-        for (key, value) in self.mods.clone() {
-            let mod_version: PackMod = value.clone().try_into().expect("try_into");
-            let dl_path = config.install_path.clone().unwrap() + &mod_version.file_name;
+    pub fn install(
+        &mut self,
+        client: &ApiClient,
+        config: &Configuration,
+        mc_ver: Option<&MCVersion>,
+        side: Option<Side>,
+        force: bool,
+    ) -> Result<(), MapitoError> {
+        let install_path = match &config.install_path {
+            Some(path) => PathBuf::from(path),
+            None => return Err(MapitoError::Config("No install path given".to_string())),
+        };
+        self.install_to(&install_path, client, config, mc_ver, side, force)
+    }
+
+    /// Like [`Pack::install`], but into the given instance root.
+    pub(super) fn install_to(
+        &mut self,
+        install_path: &Path,
+        client: &ApiClient,
+        config: &Configuration,
+        mc_ver: Option<&MCVersion>,
+        side: Option<Side>,
+        force: bool,
+    ) -> Result<(), MapitoError> {
+        let target = self.target(mc_ver)?;
+        self.verify_entries(client, config, &target)?;
+        let locks = self.locks(&target)?;
+        let mut skipped = Vec::new();
+        for (key, entry) in self.entries()? {
+            if !locks.iter().any(|(k, _)| k == &key) {
+                println!("'{key}' is not locked for Minecraft {target}, update the pack first.");
+            }
+            if let Some(side) = side.filter(|side| !entry.used_on(*side)) {
+                println!("Skipping '{key}', it is not used on the {side}.");
+                skipped.push(key);
+            }
+        }
+        for (key, mod_version) in &locks {
+            mod_version.validate(key)?;
+        }
+        for (key, mod_version) in locks {
+            if skipped.contains(&key) {
+                continue;
+            }
+            let dl_path = install_path
+                .join(mod_version.project_type.install_dir())
+                .join(&mod_version.file_name);
+            let dl_path = dl_path.to_string_lossy();
+            if let (Source::Path, Some(path)) = (mod_version.source, &mod_version.path) {
+                let local = PathBuf::from(&config.pack_path).join(path);
+                println!("Copying '{key}' from '{}' to '{dl_path}'", local.display());
+                write_verified(
+                    &dl_path,
+                    &local.to_string_lossy(),
+                    &mod_version.sha512,
+                    &read(&local)?,
+                )?;
+                continue;
+            }
             println!("Downloading '{key}' to '{dl_path}' ");
+            if !mod_version.source.is_modrinth() {
+                client.download_file(&dl_path, &mod_version.file_url, &mod_version.sha512)?;
+                continue;
+            }
             match client.download_file(&dl_path, &mod_version.file_url, &mod_version.sha512) {
                 Ok(_) => {}
-                Err(_) => {
+                Err(e) => {
+                    println!("{e}");
                     println!(
                         "Downloading '{key}' failed. Update pack entry to resolve possible errors and try again?"
                     );
                     if confirm_input() {
-                        let (fetched, _) = self.fetch_mod(&key, client);
-                        self.mods.insert(
-                            key.to_string(),
-                            toml::Value::try_from(&fetched).expect("try_from"),
-                        );
-                        self.save(config);
+                        let (fetched, _) =
+                            self.fetch_mod(&key, &target, mod_version.project_type, client)?;
+                        let (file_url, sha512) = (fetched.file_url.clone(), fetched.sha512.clone());
+                        self.set_lock(&key, &target, fetched)?;
+                        self.save(config)?;
                         println!("Retry Downloading '{key}' to '{dl_path}'");
-                        client
-                            .download_file(&dl_path, &fetched.file_url, &fetched.sha512)
-                            .expect("download_file");
+                        client.download_file(&dl_path, &file_url, &sha512)?;
                     } else {
                         println!("Could not download '{key}'");
                     }
                 }
             };
         }
+        apply_overrides(
+            &Pack::dir_path(&self.name, config),
+            install_path,
+            side,
+            force,
+        )
     }
 
-    /// Get a single Pack mod with its Minecraft Versions
-    fn fetch_mod(&self, mod_slug: &String, client: &ApiClient) -> (PackMod, Vec<MCVersion>) {
-        let project_version = client
-            .get_project_version(mod_slug, &self.version_info)
-            .expect("get_project_version");
-        (
-            PackMod {
-                name: project_version.name,
-                verstion_type: project_version.version_type,
-                version_number: project_version.version_number,
-                file_url: project_version.files[0].url.clone(),
-                sha512: project_version.files[0].hashes["sha512"]
-                    .to_string()
-                    .replace("\"", ""),
-                file_name: project_version.files[0].filename.clone(),
-                dependencies: project_version.dependencies,
+    /// the target Minecraft version of this pack matching `mc_ver`, the main one by default
+    pub(super) fn target(&self, mc_ver: Option<&MCVersion>) -> Result<MCVersion, MapitoError> {
+        match mc_ver {
+            Some(mc_ver) => match self.version_info.all_targets().find(|t| *t == mc_ver) {
+                Some(target) => Ok(target.clone()),
+                None => Err(MapitoError::IncompatibleVersion {
+                    project: self.name.clone(),
+                    mc_ver: mc_ver.clone(),
+                }),
             },
-            project_version.game_versions,
-        )
+            None => Ok(self.version_info.mc_ver.clone()),
+        }
+    }
+
+    /// the pinned loader version for `target`, or the newest one the meta api of the loader
+    /// lists when none is pinned. `None` when the loader has no meta api.
+    pub(super) fn loader_version(
+        &self,
+        meta: &MetaClient,
+        target: &MCVersion,
+    ) -> Result<Option<String>, MapitoError> {
+        if let Some(version) = self.version_info.for_target(target).loader_version {
+            return Ok(Some(version));
+        }
+        let loader = &self.version_info.loader;
+        let Some(versions) = meta.loader_versions(loader, target)? else {
+            return Ok(None);
+        };
+        let version = versions
+            .into_iter()
+            .next()
+            .ok_or(MapitoError::IncompatibleVersion {
+                project: loader.to_string(),
+                mc_ver: target.clone(),
+            })?;
+        println!("Using {loader} {version}, pin another one with 'pack pin-loader'.");
+        Ok(Some(version))
+    }
+
+    /// Get a single Pack mod for one target Minecraft version with its Minecraft Versions
+    fn fetch_mod(
+        &self,
+        mod_slug: &str,
+        target: &MCVersion,
+        project_type: ProjectType,
+        client: &ApiClient,
+    ) -> Result<(PackMod, Vec<MCVersion>), MapitoError> {
+        let version_desc = self.version_info.for_target(target);
+        let project_version =
+            client.get_project_version(mod_slug, &version_desc, project_type, None)?;
+        let game_versions = project_version.game_versions.clone();
+        Ok((
+            PackMod::from_version(project_version, project_type, &version_desc)?,
+            game_versions,
+        ))
     }
 }
 
@@ -211,43 +721,52 @@ impl Display for Pack {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Name: {}, MC Version: {}, ReleaseTypes: {}, ModLoader: {}",
+            "Name: {}, MC Version: {}{}{}, ReleaseTypes: {}, ModLoader: {}{}{}",
             self.name,
             self.version_info.mc_ver,
+            self.version_info
+                .targets
+                .iter()
+                .map(|target| format!(" +{target}"))
+                .collect::<String>(),
+            match &self.version_info.mc_range {
+                Some(range) => format!(" (accepts {range})"),
+                None => String::new(),
+            },
             self.version_info
                 .version_types
                 .iter()
                 .map(|vt| vt.to_string() + " ")
                 .collect::<String>(),
-            self.version_info.loader
+            self.version_info.loader,
+            match &self.version_info.loader_version {
+                Some(version) => format!(" {version}"),
+                None => String::new(),
+            },
+            match &self.version_info.mod_loaders()[1..] {
+                [] => String::new(),
+                fallbacks => format!(
+                    " (accepts {})",
+                    fallbacks
+                        .iter()
+                        .map(Loader::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            }
         )
     }
 }
 
-pub fn list_packs(config: Configuration) {
-    let dirs = match read_dir(&config.pack_path) {
-        Ok(d) => d,
-        Err(e) => {
-            panic!("{}", e)
-        }
-    };
-
-    for entry in dirs {
-        match entry {
-            Ok(entry) => println!(
-                "{}",
-                Pack::open(
-                    &entry
-                        .path()
-                        .file_stem()
-                        .expect("file_stem")
-                        .to_owned()
-                        .into_string()
-                        .expect("into_string"),
-                    &config
-                )
-            ),
-            Err(error) => panic!("{error}"),
+pub fn list_packs(config: Configuration) -> Result<(), MapitoError> {
+    for entry in read_dir(&config.pack_path)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "mtpck") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            println!("{}", Pack::read(&path, name)?);
         }
     }
+    Ok(())
 }
@@ -0,0 +1,560 @@
+use std::{
+    collections::BTreeMap,
+    fs::{copy, create_dir_all, read, read_to_string, remove_dir_all},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cli::input::confirm_input,
+    config::Configuration,
+    mc_info::{Loader, MCVersion, ProjectType, Side, SideSupport},
+    metaapi::client::MetaClient,
+    mrapi::client::ApiClient,
+    pack::{
+        is_plain_file_name,
+        overrides::{collect_files, OVERRIDES},
+        pack::Pack,
+        PackMod, Source,
+    },
+    util::{
+        error::MapitoError,
+        fs::{write_atomic, FileLock},
+    },
+};
+
+/// file format version of the packwiz files mapito writes
+const PACK_FORMAT: &str = "packwiz:1.1.0";
+/// hash format mapito uses for the index and its files
+const INDEX_HASH_FORMAT: &str = "sha256";
+
+/// `pack.toml` of a packwiz pack
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PwPack {
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pack_format: String,
+    index: PwIndexRef,
+    versions: PwVersions,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PwIndexRef {
+    file: String,
+    #[serde(default)]
+    hash_format: String,
+    #[serde(default)]
+    hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PwVersions {
+    minecraft: MCVersion,
+    /// loader name to loader version
+    #[serde(flatten)]
+    loaders: BTreeMap<String, String>,
+}
+
+/// `index.toml` of a packwiz pack
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PwIndex {
+    #[serde(default)]
+    hash_format: String,
+    #[serde(default)]
+    files: Vec<PwIndexFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PwIndexFile {
+    file: String,
+    #[serde(default)]
+    hash: String,
+    #[serde(default, skip_serializing_if = "<&bool as std::ops::Not>::not")]
+    metafile: bool,
+}
+
+/// a `.pw.toml` metafile describing one mod
+#[derive(Serialize, Deserialize)]
+struct PwMod {
+    name: String,
+    filename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    side: Option<String>,
+    download: PwDownload,
+    #[serde(default, skip_serializing_if = "PwUpdate::is_empty")]
+    update: PwUpdate,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PwDownload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    hash_format: String,
+    hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PwUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modrinth: Option<PwModrinth>,
+}
+
+impl PwUpdate {
+    fn is_empty(&self) -> bool {
+        self.modrinth.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PwModrinth {
+    mod_id: String,
+    version: String,
+}
+
+/// Create a pack from the packwiz pack in `dir`.
+/// Metafiles that update from modrinth are resolved to the recorded version and file, other
+/// metafiles become `url` entries when they have a sha512. All other files of the index are
+/// copied to the overrides of the pack once all entries are converted, replacing the override
+/// files of a pack that is overwritten. Entries that could not be converted are listed.
+pub fn import_packwiz(
+    dir: &Path,
+    client: &ApiClient,
+    config: &Configuration,
+) -> Result<(), MapitoError> {
+    let pw_pack: PwPack = toml::from_str(&read_to_string(dir.join("pack.toml"))?)?;
+    let index_path = dir.join(&pw_pack.index.file);
+    let index: PwIndex = toml::from_str(&read_to_string(&index_path)?)?;
+    let index_dir = index_path.parent().unwrap_or(dir);
+    if !is_plain_file_name(&pw_pack.name) {
+        return Err(MapitoError::InvalidEntry {
+            entry: "name".to_string(),
+            reason: format!(
+                "is '{}', which can not be used as a pack name",
+                pw_pack.name
+            ),
+        });
+    }
+
+    let _lock = FileLock::acquire(&Pack::file_path(&pw_pack.name, config))?;
+    let pack_dir = Pack::dir_path(&pw_pack.name, config);
+    if Pack::file_path(&pw_pack.name, config).exists() {
+        println!(
+            "A pack named {} already exists, overwrite it and its override files?",
+            pw_pack.name
+        );
+        if !confirm_input() {
+            return Ok(());
+        }
+    } else if pack_dir.is_dir() {
+        println!(
+            "'{}' still holds the override files of a removed pack named {}, replace them?",
+            pack_dir.display(),
+            pw_pack.name
+        );
+        if !confirm_input() {
+            return Ok(());
+        }
+    }
+    let mut pack = Pack::new();
+    pack.name = pw_pack.name;
+    pack.version_info.mc_ver = pw_pack.versions.minecraft;
+    match pw_pack.versions.loaders.into_iter().next() {
+        Some((loader, version)) => {
+            pack.version_info.loader = loader.parse().map_err(|e| MapitoError::InvalidEntry {
+                entry: "versions".to_string(),
+                reason: format!("lists an {e}"),
+            })?;
+            pack.version_info.loader_version = Some(version);
+        }
+        None => pack.version_info.loader = config.loader.clone(),
+    }
+
+    let mut metafiles = Vec::new();
+    let mut override_files = Vec::new();
+    let mut skipped: Vec<(String, String)> = Vec::new();
+    for file in index.files {
+        let path = Path::new(&file.file);
+        if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+            skipped.push((file.file, "is outside of the pack".to_string()));
+        } else if file.metafile || file.file.ends_with(".pw.toml") {
+            let body = match read_to_string(index_dir.join(&file.file)) {
+                Ok(body) => body,
+                Err(e) => {
+                    skipped.push((file.file, format!("could not be read: {e}")));
+                    continue;
+                }
+            };
+            match toml::from_str::<PwMod>(&body) {
+                Ok(pw_mod) => metafiles.push((file.file, pw_mod)),
+                Err(e) => skipped.push((file.file, format!("is not a valid metafile: {e}"))),
+            }
+        } else {
+            override_files.push(file.file);
+        }
+    }
+
+    let version_ids: Vec<String> = metafiles
+        .iter()
+        .filter_map(|(_, m)| m.update.modrinth.as_ref().map(|mr| mr.version.clone()))
+        .collect();
+    let versions = client.get_versions(&version_ids)?;
+    let project_ids: Vec<String> = versions.iter().map(|v| v.project_id.clone()).collect();
+    let projects = client.get_projects(&project_ids)?;
+
+    for (file, pw_mod) in metafiles {
+        let key = file
+            .rsplit('/')
+            .next()
+            .unwrap_or(&file)
+            .trim_end_matches(".pw.toml")
+            .to_string();
+        let download = &pw_mod.download;
+        if let Some(modrinth) = &pw_mod.update.modrinth {
+            let Some(version) = versions.iter().find(|v| v.id == modrinth.version) else {
+                skipped.push((key, format!("modrinth has no version {}", modrinth.version)));
+                continue;
+            };
+            let Some(project) = projects.iter().find(|p| p.id == version.project_id) else {
+                skipped.push((key, format!("modrinth has no project {}", modrinth.mod_id)));
+                continue;
+            };
+            let Some(api_file) = version.files.iter().find(|f| {
+                f.hashes
+                    .get(&download.hash_format)
+                    .and_then(|hash| hash.as_str())
+                    .is_some_and(|hash| hash.eq_ignore_ascii_case(&download.hash))
+            }) else {
+                skipped.push((
+                    key,
+                    format!("no file of version {} has the recorded hash", version.id),
+                ));
+                continue;
+            };
+            let Ok(sha512) = api_file.sha512() else {
+                skipped.push((
+                    key,
+                    format!("{} has no sha512 on modrinth", api_file.filename),
+                ));
+                continue;
+            };
+            let project_type = ProjectType::of_project(project, &pack.version_info.loader);
+            let mut entry =
+                match PackMod::from_version(version.clone(), project_type, &pack.version_info) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        skipped.push((key, e.to_string()));
+                        continue;
+                    }
+                };
+            entry.sha512 = sha512.to_string();
+            entry.file_url = api_file.url.clone();
+            entry.file_name = api_file.filename.clone();
+            entry.client_side = project.client_side;
+            entry.server_side = project.server_side;
+            if pack.mods.contains_key(&project.slug) {
+                skipped.push((file, format!("is a second entry named {}", project.slug)));
+                continue;
+            }
+            pack.mods
+                .insert(project.slug.clone(), toml::Value::try_from(&entry)?);
+            continue;
+        }
+        let url = match (&download.url, &download.mode) {
+            (_, Some(mode)) if mode != "url" => {
+                skipped.push((
+                    key,
+                    format!("downloads with mode '{mode}' are not supported"),
+                ));
+                continue;
+            }
+            (Some(url), _) => url.clone(),
+            (None, _) => {
+                skipped.push((key, "has no download url".to_string()));
+                continue;
+            }
+        };
+        if download.hash_format != "sha512" {
+            skipped.push((
+                key,
+                format!("only has a {} hash, sha512 is needed", download.hash_format),
+            ));
+            continue;
+        }
+        let mut entry = PackMod::from_file(pw_mod.name, Source::Url, url, download.hash.clone());
+        entry.file_name = pw_mod.filename;
+        entry.project_type = match file.split('/').next() {
+            Some("resourcepacks") => ProjectType::ResourcePack,
+            Some("shaderpacks") => ProjectType::ShaderPack,
+            Some("plugins") => ProjectType::Plugin,
+            _ => ProjectType::Mod,
+        };
+        (entry.client_side, entry.server_side) = match pw_mod.side.as_deref() {
+            Some("client") => (SideSupport::Required, SideSupport::Unsupported),
+            Some("server") => (SideSupport::Unsupported, SideSupport::Required),
+            _ => (SideSupport::Unknown, SideSupport::Unknown),
+        };
+        if pack.mods.contains_key(&key) {
+            skipped.push((file, format!("is a second entry named {key}")));
+            continue;
+        }
+        pack.mods.insert(key, toml::Value::try_from(&entry)?);
+    }
+
+    if pack_dir.is_dir() {
+        remove_dir_all(&pack_dir)?;
+    }
+    let overrides = pack_dir.join(OVERRIDES);
+    for file in override_files {
+        println!("Copying override '{file}'");
+        let target = overrides.join(&file);
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)?;
+        }
+        copy(index_dir.join(&file), target)?;
+    }
+    pack.save(config)?;
+    println!(
+        "Imported {} entries into {}, Minecraft-{} {}",
+        pack.mods.len(),
+        pack.name,
+        pack.version_info.mc_ver,
+        pack.version_info.loader
+    );
+    if !skipped.is_empty() {
+        println!("Could not convert:");
+        for (key, reason) in skipped {
+            println!("  {key}: {reason}");
+        }
+    }
+    Ok(())
+}
+
+/// Write a pack as a packwiz pack to `dir`: a `pack.toml`, an `index.toml` and a `.pw.toml`
+/// metafile for every entry, so it can be installed with packwiz-installer.
+/// Local files and the common overrides of the pack are copied next to the metafiles.
+/// Without a pinned loader version the newest one is looked up, packwiz needs it to install
+/// the loader.
+pub fn export_packwiz(
+    pack: &Pack,
+    dir: &Path,
+    meta: &MetaClient,
+    config: &Configuration,
+    mc_ver: Option<&MCVersion>,
+) -> Result<(), MapitoError> {
+    let target = pack.target(mc_ver)?;
+    let loaders = packwiz_loaders(pack, meta, &target)?;
+    println!(
+        "Exporting {} as a packwiz pack to '{}'",
+        pack.name,
+        dir.display()
+    );
+    let entries = pack.entries()?;
+    let locks = pack.locks(&target)?;
+    for (key, _) in &entries {
+        if !locks.iter().any(|(k, _)| k == key) {
+            println!("'{key}' is not locked for Minecraft {target}, update the pack first.");
+        }
+    }
+    let mut files: BTreeMap<String, (Vec<u8>, bool)> = BTreeMap::new();
+    for (key, lock) in locks {
+        let entry = entries
+            .iter()
+            .find(|(k, _)| k == &key)
+            .map_or(&lock, |(_, entry)| entry);
+        lock.validate(&key)?;
+        let install_dir = lock.project_type.install_dir();
+        if let (Source::Path, Some(path)) = (lock.source, &lock.path) {
+            let body = read(PathBuf::from(&config.pack_path).join(path))?;
+            files.insert(format!("{install_dir}/{}", lock.file_name), (body, false));
+            continue;
+        }
+        let side = match (entry.used_on(Side::Client), entry.used_on(Side::Server)) {
+            (true, false) => "client",
+            (false, true) => "server",
+            _ => "both",
+        };
+        let modrinth = match (lock.source, &lock.project_id, &lock.version_id) {
+            (Source::Modrinth, Some(mod_id), Some(version)) => Some(PwModrinth {
+                mod_id: mod_id.clone(),
+                version: version.clone(),
+            }),
+            _ => None,
+        };
+        let metafile = PwMod {
+            name: lock.name.clone(),
+            filename: lock.file_name.clone(),
+            side: Some(side.to_string()),
+            download: PwDownload {
+                url: Some(lock.file_url.clone()),
+                hash_format: "sha512".to_string(),
+                hash: lock.sha512.clone(),
+                mode: None,
+            },
+            update: PwUpdate { modrinth },
+        };
+        files.insert(
+            format!("{install_dir}/{key}.pw.toml"),
+            (toml::to_string(&metafile)?.into_bytes(), true),
+        );
+    }
+    let mut overrides = BTreeMap::new();
+    let overrides_dir = Pack::dir_path(&pack.name, config).join(OVERRIDES);
+    collect_files(&overrides_dir, &overrides_dir, &mut overrides)?;
+    for (rel_path, source) in overrides {
+        files.insert(rel_path, (read(source)?, false));
+    }
+
+    let mut index = PwIndex {
+        hash_format: INDEX_HASH_FORMAT.to_string(),
+        files: Vec::new(),
+    };
+    for (rel_path, (body, metafile)) in files {
+        write_atomic(&dir.join(&rel_path), &body)?;
+        index.files.push(PwIndexFile {
+            file: rel_path,
+            hash: sha256_hex(&body),
+            metafile,
+        });
+    }
+    let index_body = toml::to_string(&index)?;
+    write_atomic(&dir.join("index.toml"), index_body.as_bytes())?;
+    let pw_pack = PwPack {
+        name: pack.name.clone(),
+        pack_format: PACK_FORMAT.to_string(),
+        index: PwIndexRef {
+            file: "index.toml".to_string(),
+            hash_format: INDEX_HASH_FORMAT.to_string(),
+            hash: sha256_hex(index_body.as_bytes()),
+        },
+        versions: PwVersions {
+            loaders,
+            minecraft: target,
+        },
+    };
+    write_atomic(
+        &dir.join("pack.toml"),
+        toml::to_string(&pw_pack)?.as_bytes(),
+    )?;
+    println!("Exported {} to '{}'", pack.name, dir.display());
+    Ok(())
+}
+
+/// loader versions for the `versions` of a packwiz pack, empty for vanilla packs
+fn packwiz_loaders(
+    pack: &Pack,
+    meta: &MetaClient,
+    target: &MCVersion,
+) -> Result<BTreeMap<String, String>, MapitoError> {
+    let loader = &pack.version_info.loader;
+    if matches!(loader, Loader::Minecraft | Loader::Datapack) {
+        return Ok(BTreeMap::new());
+    }
+    let Some(version) = pack.loader_version(meta, target)? else {
+        return Err(MapitoError::InvalidEntry {
+            entry: "loader_version".to_string(),
+            reason: format!(
+                "is needed to export {loader} packs for packwiz, \
+                pin one with 'pack pin-loader --version'"
+            ),
+        });
+    };
+    Ok(BTreeMap::from([(loader.to_string(), version)]))
+}
+
+fn sha256_hex(body: &[u8]) -> String {
+    base16ct::lower::encode_string(&Sha256::digest(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{remove_dir_all, write},
+        process,
+    };
+
+    use super::*;
+
+    const SHA512: &str = "ee5f4b3823019c88561e03fa820517c282c62cc00b634ea2e87c60c4b54f734597e44f435931f6a35ceb8f794b3f03614bc31707338f2c75c506474545cd02d9";
+
+    fn config(pack_path: &Path) -> Configuration {
+        let mut config = Configuration::new();
+        config.pack_path = pack_path.to_string_lossy().to_string();
+        config
+    }
+
+    #[test]
+    fn exported_packs_import_unchanged() {
+        let root = env::temp_dir().join(format!("mapito-packwiz-{}", process::id()));
+        let export_config = config(&root.join("packs"));
+        let import_config = config(&root.join("imported"));
+
+        let mut pack = Pack::new();
+        pack.name = "Round Trip".to_string();
+        pack.version_info.mc_ver = "1.21.5".parse().unwrap();
+        pack.version_info.loader = Loader::Quilt;
+        pack.version_info.loader_version = Some("0.26.0".to_string());
+        pack.add_url(
+            "shared",
+            "https://example.com/shared-1.0.jar".to_string(),
+            SHA512.to_string(),
+        )
+        .unwrap();
+        let mut client_only = PackMod::from_file(
+            "Client Only".to_string(),
+            Source::Url,
+            "https://example.com/client-2.0.jar".to_string(),
+            SHA512.to_string(),
+        );
+        client_only.client_side = SideSupport::Required;
+        client_only.server_side = SideSupport::Unsupported;
+        pack.mods.insert(
+            "client-only".to_string(),
+            toml::Value::try_from(&client_only).unwrap(),
+        );
+        let overrides = Pack::dir_path(&pack.name, &export_config).join(OVERRIDES);
+        create_dir_all(overrides.join("config")).unwrap();
+        write(overrides.join("config/a.cfg"), "a = 1").unwrap();
+
+        let out = root.join("out");
+        let meta = MetaClient::new(&export_config).unwrap();
+        export_packwiz(&pack, &out, &meta, &export_config, None).unwrap();
+        let client = ApiClient::new(&import_config).unwrap();
+        import_packwiz(&out, &client, &import_config).unwrap();
+
+        let imported = Pack::open("Round Trip", &import_config).unwrap();
+        assert_eq!(imported.version_info.mc_ver, pack.version_info.mc_ver);
+        assert_eq!(imported.version_info.loader, Loader::Quilt);
+        assert_eq!(
+            imported.version_info.loader_version.as_deref(),
+            Some("0.26.0")
+        );
+        let entries = imported.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        for (key, entry) in pack.entries().unwrap() {
+            let (_, imported) = entries.iter().find(|(k, _)| k == &key).unwrap();
+            assert_eq!(imported.source, Source::Url);
+            assert_eq!(imported.file_url, entry.file_url);
+            assert_eq!(imported.file_name, entry.file_name);
+            assert_eq!(imported.sha512, entry.sha512);
+            assert_eq!(imported.used_on(Side::Client), entry.used_on(Side::Client));
+            assert_eq!(imported.used_on(Side::Server), entry.used_on(Side::Server));
+        }
+        let imported_overrides = Pack::dir_path("Round Trip", &import_config).join(OVERRIDES);
+        assert_eq!(
+            read_to_string(imported_overrides.join("config/a.cfg")).unwrap(),
+            "a = 1"
+        );
+        drop(imported);
+        remove_dir_all(&root).unwrap();
+    }
+}
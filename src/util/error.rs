@@ -1,9 +1,42 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, process::ExitCode, time::Duration};
+
+use reqwest::{blocking::Response, StatusCode};
+use serde::Deserialize;
+
+use crate::{mc_info::MCVersion, mrapi::constants::RATELIMIT_RESET};
+
+/// The error body modrinth sends along with unsuccessful responses.
+#[derive(Debug, Deserialize)]
+struct ModrinthErrorBody {
+    error: String,
+    description: String,
+}
 
 #[derive(Debug)]
 enum ApiErrorKind {
     NotFound,
-    ReqwestError(reqwest::Error)
+    Status {
+        status: StatusCode,
+        url: String,
+    },
+    Modrinth {
+        status: StatusCode,
+        error: String,
+        description: String,
+    },
+    RateLimited {
+        reset: Option<Duration>,
+    },
+    Integrity {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    Deserialize {
+        endpoint: String,
+        source: serde_json::Error,
+    },
+    ReqwestError(reqwest::Error),
 }
 
 #[derive(Debug)]
@@ -13,39 +46,266 @@ pub struct ApiError {
 
 impl Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let to_display = match &self.kind {
-            ApiErrorKind::NotFound => "NotFound",
-            ApiErrorKind::ReqwestError(e) => &e.to_string()
-        };
-        write!(f, "{}", to_display)
+        match &self.kind {
+            ApiErrorKind::NotFound => write!(f, "NotFound"),
+            ApiErrorKind::Status { status, url } => write!(f, "{url} responded with {status}"),
+            ApiErrorKind::Modrinth {
+                status,
+                error,
+                description,
+            } => write!(f, "{status} {error}: {description}"),
+            ApiErrorKind::RateLimited { reset: Some(reset) } => write!(
+                f,
+                "rate limit exceeded, resets in {} seconds",
+                reset.as_secs()
+            ),
+            ApiErrorKind::RateLimited { reset: None } => write!(f, "rate limit exceeded"),
+            ApiErrorKind::Integrity {
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "integrity check of '{url}' failed\n expected sha512: {expected}\n actual sha512: {actual}"
+            ),
+            ApiErrorKind::Deserialize { endpoint, source } => {
+                write!(f, "unexpected response from {endpoint}: {source}")
+            }
+            ApiErrorKind::ReqwestError(e) => write!(f, "{e}"),
+        }
     }
 }
 
 impl Error for ApiError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match &self.kind {
+            ApiErrorKind::Deserialize { source, .. } => Some(source),
+            ApiErrorKind::ReqwestError(e) => Some(e),
+            _ => None,
+        }
     }
+}
 
-    fn description(&self) -> &str {
-        "description() is deprecated; use Display"
+impl ApiError {
+    pub fn not_found() -> Self {
+        ApiError {
+            kind: ApiErrorKind::NotFound,
+        }
     }
 
-    fn cause(&self) -> Option<&dyn Error> {
-        self.source()
+    /// A downloaded file whose sha512 does not match the expected one.
+    pub fn integrity(url: &str, expected: &str, actual: &str) -> Self {
+        ApiError {
+            kind: ApiErrorKind::Integrity {
+                url: url.to_string(),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            },
+        }
     }
 
-    //fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {}
-}
+    /// A response body that could not be deserialized into the expected type.
+    pub fn deserialize(endpoint: &str, source: serde_json::Error) -> Self {
+        ApiError {
+            kind: ApiErrorKind::Deserialize {
+                endpoint: endpoint.to_string(),
+                source,
+            },
+        }
+    }
 
-impl ApiError {
-    pub fn not_found() -> Self {
-        ApiError { kind: ApiErrorKind::NotFound }
+    /// Build the error for an unsuccessful response, consuming its body.
+    pub fn from_response(response: Response) -> Self {
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let reset = response
+                .headers()
+                .get(RATELIMIT_RESET)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs);
+            return ApiError {
+                kind: ApiErrorKind::RateLimited { reset },
+            };
+        }
+        let url = response.url().to_string();
+        let body = response.text().unwrap_or_default();
+        let kind = match serde_json::from_str::<ModrinthErrorBody>(&body) {
+            Ok(body) => ApiErrorKind::Modrinth {
+                status,
+                error: body.error,
+                description: body.description,
+            },
+            Err(_) if status == StatusCode::NOT_FOUND => ApiErrorKind::NotFound,
+            Err(_) => ApiErrorKind::Status { status, url },
+        };
+        ApiError { kind }
+    }
+
+    /// Whether sending the same request again might succeed.
+    pub fn is_retryable(&self) -> bool {
+        match &self.kind {
+            ApiErrorKind::RateLimited { .. } => true,
+            ApiErrorKind::Status { status, .. } | ApiErrorKind::Modrinth { status, .. } => {
+                status.is_server_error()
+            }
+            ApiErrorKind::ReqwestError(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            _ => false,
+        }
     }
 
+    /// Time until the rate limit resets, if this error was caused by hitting it.
+    pub fn rate_limit_reset(&self) -> Option<Duration> {
+        match &self.kind {
+            ApiErrorKind::RateLimited { reset } => *reset,
+            _ => None,
+        }
+    }
 }
 
 impl From<reqwest::Error> for ApiError {
     fn from(value: reqwest::Error) -> Self {
-        ApiError { kind: ApiErrorKind::ReqwestError(value) }
+        ApiError {
+            kind: ApiErrorKind::ReqwestError(value),
+        }
+    }
+}
+
+/// Any error that can occur while running mapito.
+#[derive(Debug)]
+pub enum MapitoError {
+    /// Talking to the modrinth api failed.
+    Api(ApiError),
+    /// Talking to the github api failed.
+    Github(ApiError),
+    /// Talking to the meta api of a mod loader failed.
+    Meta(ApiError),
+    /// Reading or writing a local file failed.
+    Io(std::io::Error),
+    /// A pack or config file could not be parsed.
+    TomlDe(toml::de::Error),
+    /// A pack or config could not be serialized.
+    TomlSer(toml::ser::Error),
+    /// The configuration is missing or contains invalid values.
+    Config(String),
+    /// No pack with the given name exists in the pack path.
+    PackNotFound(String),
+    /// A project has no version for the requested Minecraft version.
+    IncompatibleVersion { project: String, mc_ver: MCVersion },
+    /// The downloaded file does not match the hash recorded for it.
+    HashMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    /// A Minecraft version that modrinth does not know.
+    UnknownMCVersion(MCVersion),
+    /// A pack entry that is missing information or contradicts itself.
+    InvalidEntry { entry: String, reason: String },
+}
+
+impl Display for MapitoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapitoError::Api(e) => write!(f, "modrinth api: {e}"),
+            MapitoError::Github(e) => write!(f, "github api: {e}"),
+            MapitoError::Meta(e) => write!(f, "loader meta api: {e}"),
+            MapitoError::Io(e) => write!(f, "{e}"),
+            MapitoError::TomlDe(e) => write!(f, "invalid toml: {}", e.message()),
+            MapitoError::TomlSer(e) => write!(f, "could not serialize toml: {e}"),
+            MapitoError::Config(msg) => write!(f, "config: {msg}"),
+            MapitoError::PackNotFound(name) => write!(f, "no pack named '{name}' found"),
+            MapitoError::IncompatibleVersion { project, mc_ver } => {
+                write!(
+                    f,
+                    "'{project}' has no compatible version for Minecraft {mc_ver}"
+                )
+            }
+            MapitoError::HashMismatch {
+                file,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "integrity check of '{file}' failed\n expected sha512: {expected}\n actual sha512: {actual}"
+            ),
+            MapitoError::UnknownMCVersion(mc_ver) => {
+                write!(f, "'{mc_ver}' is not a known Minecraft version")
+            }
+            MapitoError::InvalidEntry { entry, reason } => {
+                write!(f, "pack entry '{entry}' {reason}")
+            }
+        }
+    }
+}
+
+impl Error for MapitoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MapitoError::Api(e) | MapitoError::Github(e) | MapitoError::Meta(e) => Some(e),
+            MapitoError::Io(e) => Some(e),
+            MapitoError::TomlDe(e) => Some(e),
+            MapitoError::TomlSer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl MapitoError {
+    /// Process exit code for this error, following the BSD sysexits convention.
+    pub fn exit_code(&self) -> ExitCode {
+        let code: u8 = match self {
+            MapitoError::Api(_) | MapitoError::Github(_) | MapitoError::Meta(_) => 69,
+            MapitoError::Io(_) => 74,
+            MapitoError::TomlDe(_) | MapitoError::TomlSer(_) => 65,
+            MapitoError::Config(_) => 78,
+            MapitoError::PackNotFound(_) => 66,
+            MapitoError::IncompatibleVersion { .. } => 65,
+            MapitoError::HashMismatch { .. } => 65,
+            MapitoError::UnknownMCVersion(_) => 65,
+            MapitoError::InvalidEntry { .. } => 65,
+        };
+        ExitCode::from(code)
+    }
+}
+
+impl From<ApiError> for MapitoError {
+    fn from(value: ApiError) -> Self {
+        match value.kind {
+            ApiErrorKind::Integrity {
+                url,
+                expected,
+                actual,
+            } => MapitoError::HashMismatch {
+                file: url,
+                expected,
+                actual,
+            },
+            kind => MapitoError::Api(ApiError { kind }),
+        }
+    }
+}
+
+impl From<reqwest::Error> for MapitoError {
+    fn from(value: reqwest::Error) -> Self {
+        MapitoError::Api(value.into())
+    }
+}
+
+impl From<std::io::Error> for MapitoError {
+    fn from(value: std::io::Error) -> Self {
+        MapitoError::Io(value)
+    }
+}
+
+impl From<toml::de::Error> for MapitoError {
+    fn from(value: toml::de::Error) -> Self {
+        MapitoError::TomlDe(value)
+    }
+}
+
+impl From<toml::ser::Error> for MapitoError {
+    fn from(value: toml::ser::Error) -> Self {
+        MapitoError::TomlSer(value)
     }
 }
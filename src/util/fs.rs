@@ -0,0 +1,175 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::OsString,
+    fs::{create_dir_all, remove_file, rename, File, OpenOptions, TryLockError},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+};
+
+thread_local! {
+    /// locks this process holds, so taking a lock twice shares it instead of deadlocking
+    static HELD: RefCell<HashMap<PathBuf, Weak<FileLock>>> = RefCell::new(HashMap::new());
+}
+
+/// An exclusive lock on a file, held until the last handle to it is dropped.
+///
+/// The lock is taken on a sidecar `.<file>.lock` so that the locked file itself
+/// can still be replaced by a rename while the lock is held.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Block until the exclusive lock for `path` is acquired.
+    /// When this process already holds it, the held lock is shared.
+    pub fn acquire(path: &Path) -> io::Result<Rc<FileLock>> {
+        if let Some(held) = HELD.with_borrow(|held| held.get(path).and_then(Weak::upgrade)) {
+            return Ok(held);
+        }
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(sibling(path, ".lock"))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                println!(
+                    "Waiting for another mapito process to release '{}'",
+                    path.display()
+                );
+                file.lock()?;
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+        let lock = Rc::new(FileLock {
+            file,
+            path: path.to_path_buf(),
+        });
+        HELD.with_borrow_mut(|held| held.insert(path.to_path_buf(), Rc::downgrade(&lock)));
+        Ok(lock)
+    }
+
+    /// Remove the locked file together with its lock file, for files that are gone for good.
+    pub fn remove(&self) -> io::Result<()> {
+        remove_file(&self.path)?;
+        let _ = remove_file(sibling(&self.path, ".lock"));
+        Ok(())
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        HELD.with_borrow_mut(|held| held.remove(&self.path));
+        let _ = self.file.unlock();
+    }
+}
+
+/// Write `contents` to `path` without ever leaving a partially written file behind.
+///
+/// The data is written to a temporary file next to `path`, flushed to disk and then
+/// renamed over the target. Callers that race with other processes for the same file hold
+/// its [`FileLock`] around the read and the write.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let tmp_path = sibling(path, ".tmp");
+    let result = (|| {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(contents)?;
+        tmp.sync_all()?;
+        rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Path of a hidden helper file living next to `path`, e.g. `.config.toml.lock`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{create_dir, read_dir, read_to_string, remove_dir_all},
+        process,
+    };
+
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mapito-fs-{name}-{}", process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<OsString> {
+        let mut names: Vec<OsString> = read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn writes_replace_the_file_through_a_temporary_file() {
+        let dir = test_dir("write");
+        let path = dir.join("pack.mtpck");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(read_to_string(&path).unwrap(), "new");
+        assert_eq!(file_names(&dir), vec![OsString::from("pack.mtpck")]);
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_writes_remove_the_temporary_file() {
+        let dir = test_dir("failed");
+        let path = dir.join("taken");
+        create_dir(&path).unwrap();
+        write_atomic(&path.join("inner"), b"keep").unwrap();
+        assert!(write_atomic(&path, b"lost").is_err());
+        assert_eq!(file_names(&dir), vec![OsString::from("taken")]);
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locks_are_shared_within_the_process() {
+        let dir = test_dir("lock");
+        let path = dir.join("config.toml");
+        let other_handle = || File::open(sibling(&path, ".lock")).unwrap();
+
+        let first = FileLock::acquire(&path).unwrap();
+        let second = FileLock::acquire(&path).unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(matches!(
+            other_handle().try_lock(),
+            Err(TryLockError::WouldBlock)
+        ));
+        drop(first);
+        assert!(matches!(
+            other_handle().try_lock(),
+            Err(TryLockError::WouldBlock)
+        ));
+        drop(second);
+        assert!(other_handle().try_lock().is_ok());
+        remove_dir_all(&dir).unwrap();
+    }
+}
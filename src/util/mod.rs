@@ -1,4 +1,5 @@
 pub mod error;
+pub mod fs;
 
 /// Number of Bytes in MiB
 pub const MIB: u64 = 0x100000;